@@ -1,9 +1,10 @@
 use std::fs::File;
 use std::io;
-use std::io::{BufWriter, Write};
+use std::io::BufWriter;
 
 use rand::prelude::*;
 
+use delaunay_mesh::export::svg::Svg;
 use delaunay_mesh::geo::{Bbox, Vec2};
 use delaunay_mesh::DelaunayMesh;
 
@@ -42,7 +43,9 @@ fn main() -> io::Result<()> {
     }
 
     let mut out = BufWriter::new(File::create("recursive-triangulation.svg")?);
-    dump_svg(&mut out, &mesh)
+    Svg::new(&mesh).write(&mut out)?;
+
+    Ok(())
 }
 
 pub fn rand_vec2(rng: &mut impl Rng, bbox: Bbox) -> Vec2 {
@@ -55,33 +58,3 @@ pub fn rand_vec2(rng: &mut impl Rng, bbox: Bbox) -> Vec2 {
 
     Vec2::new(x, y)
 }
-
-pub fn dump_svg(out: &mut impl Write, dmesh: &DelaunayMesh) -> io::Result<()> {
-    let bbox = dmesh.bbox();
-    let d = bbox.dimensions();
-
-    writeln!(
-        out,
-        r#"<?xml version="1.0" encoding="UTF-8"?>
-<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
-<svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="{x} {y} {w} {h}">
-<rect x="{x}" y="{y}" width="{w}" height="{h}" stroke="none" fill="white" />
-             "#,
-        x = bbox.min().x,
-        y = bbox.min().y,
-        w = d.x,
-        h = d.y,
-    )?;
-
-    for (tri, _) in dmesh.triangles() {
-        let [a, b, c] = dmesh.triangle_vertices(tri);
-
-        writeln!(
-            out,
-            r#"<polygon points="{},{} {},{} {},{}" fill="none" stroke="black" />"#,
-            a.x, a.y, b.x, b.y, c.x, c.y
-        )?;
-    }
-
-    writeln!(out, "</svg>")
-}
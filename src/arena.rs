@@ -1,34 +1,66 @@
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
 
+/// A collection of elements of type `T` addressed by stable ids. The ids are tagged with `Tag`,
+/// which is `T` itself unless the ids must be shared by arenas of different types.
+///
+/// With the `serde` feature the free list is checked on deserialization like in `from_raw`.
 #[derive(Debug)]
-pub struct Arena<T> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "ArenaData<T>",
+        bound(deserialize = "T: serde::Deserialize<'de>")
+    )
+)]
+pub struct Arena<T, Tag = T> {
     data: Vec<Node<T>>,
     first_free: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tag: PhantomData<Tag>,
 }
 
+// the serialized part of an `Arena`, it's validated before being turned into an arena.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ArenaData<T> {
+    data: Vec<Node<T>>,
+    first_free: Option<usize>,
+}
+
+/// A slot of an `Arena`, free slots form a linked list starting at `Arena::first_free`.
 #[derive(Debug)]
-enum Node<T> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum Node<T> {
     Free { next_free: Option<usize> },
     Occupied(T),
 }
 
+/// The id of an element in an `Arena`. With the `serde` feature it's serialized as the plain
+/// index of its slot.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent, bound = ""))]
 pub struct ArenaId<Tag> {
     ix: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
     tag: std::marker::PhantomData<Tag>,
 }
 
-impl<T> Arena<T> {
+impl<T, Tag> Arena<T, Tag> {
     pub fn new() -> Self {
         Arena {
             data: vec![],
             first_free: None,
+            tag: PhantomData,
         }
     }
 
-    pub fn push(&mut self, v: T) -> ArenaId<T> {
+    pub fn push(&mut self, v: T) -> ArenaId<Tag> {
         match self.first_free {
             None => {
                 self.data.push(Node::Occupied(v));
@@ -48,7 +80,7 @@ impl<T> Arena<T> {
         }
     }
 
-    pub fn remove(&mut self, id: ArenaId<T>) -> Option<T> {
+    pub fn remove(&mut self, id: ArenaId<Tag>) -> Option<T> {
         match self.data.get_mut(id.ix)? {
             cell @ Node::Occupied(_) => {
                 let mut out = Node::Free {
@@ -67,25 +99,84 @@ impl<T> Arena<T> {
         }
     }
 
-    pub fn get(&self, id: ArenaId<T>) -> Option<&T> {
+    pub fn get(&self, id: ArenaId<Tag>) -> Option<&T> {
         match self.data.get(id.ix)? {
             Node::Occupied(t) => Some(t),
             Node::Free { .. } => None,
         }
     }
 
-    pub fn get_mut(&mut self, id: ArenaId<T>) -> Option<&mut T> {
+    pub fn get_mut(&mut self, id: ArenaId<Tag>) -> Option<&mut T> {
         match self.data.get_mut(id.ix)? {
             Node::Occupied(t) => Some(t),
             Node::Free { .. } => None,
         }
     }
 
+    /// Build an arena from its raw slots, returns `None` if the free list is not made of exactly
+    /// all the free slots.
+    pub(crate) fn from_raw(data: Vec<Node<T>>, first_free: Option<usize>) -> Option<Self> {
+        let nfree = data
+            .iter()
+            .filter(|n| matches!(n, Node::Free { .. }))
+            .count();
+
+        let mut visited = 0;
+        let mut cur = first_free;
+        while let Some(i) = cur {
+            if visited == nfree {
+                return None;
+            }
+            visited += 1;
+
+            match data.get(i)? {
+                Node::Free { next_free } => cur = *next_free,
+                Node::Occupied(_) => return None,
+            }
+        }
+
+        if visited == nfree {
+            Some(Arena {
+                data,
+                first_free,
+                tag: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The raw slots of the arena along with the head of the free list.
+    pub(crate) fn raw(&self) -> (&[Node<T>], Option<usize>) {
+        (&self.data, self.first_free)
+    }
+
+    /// An arena with the same ids whose elements are the result of applying `f` to the elements
+    /// of this arena.
+    pub(crate) fn map<U>(&self, f: impl Fn(&T) -> U) -> Arena<U, Tag> {
+        let data = self
+            .data
+            .iter()
+            .map(|n| match n {
+                Node::Free { next_free } => Node::Free {
+                    next_free: *next_free,
+                },
+                Node::Occupied(t) => Node::Occupied(f(t)),
+            })
+            .collect();
+
+        Arena {
+            data,
+            first_free: self.first_free,
+            tag: PhantomData,
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &T> {
         self.enumerate().map(|(_, n)| n)
     }
 
-    pub fn enumerate(&self) -> impl Iterator<Item = (ArenaId<T>, &T)> {
+    pub fn enumerate(&self) -> impl Iterator<Item = (ArenaId<Tag>, &T)> {
         self.data.iter().enumerate().filter_map(|(i, n)| match n {
             Node::Occupied(t) => Some((ArenaId::new(i), t)),
             Node::Free { .. } => None,
@@ -93,42 +184,54 @@ impl<T> Arena<T> {
     }
 }
 
-impl<T> Default for Arena<T> {
+impl<T, Tag> Default for Arena<T, Tag> {
     fn default() -> Self {
         Arena::new()
     }
 }
 
-impl<T> Index<ArenaId<T>> for Arena<T> {
+#[cfg(feature = "serde")]
+impl<T, Tag> TryFrom<ArenaData<T>> for Arena<T, Tag> {
+    type Error = &'static str;
+
+    fn try_from(data: ArenaData<T>) -> Result<Self, Self::Error> {
+        Arena::from_raw(data.data, data.first_free).ok_or("invalid arena free list")
+    }
+}
+
+impl<T, Tag> Index<ArenaId<Tag>> for Arena<T, Tag> {
     type Output = T;
 
-    fn index(&self, ix: ArenaId<T>) -> &Self::Output {
+    fn index(&self, ix: ArenaId<Tag>) -> &Self::Output {
         self.get(ix).unwrap()
     }
 }
 
-impl<T> IndexMut<ArenaId<T>> for Arena<T> {
-    fn index_mut(&mut self, ix: ArenaId<T>) -> &mut T {
+impl<T, Tag> IndexMut<ArenaId<Tag>> for Arena<T, Tag> {
+    fn index_mut(&mut self, ix: ArenaId<Tag>) -> &mut T {
         self.get_mut(ix).unwrap()
     }
 }
 
 impl<Tag> ArenaId<Tag> {
-    fn new(ix: usize) -> Self {
+    pub(crate) fn new(ix: usize) -> Self {
         ArenaId {
             ix,
             tag: PhantomData,
         }
     }
+
+    /// The index of the slot of the element in the arena. Indices are stable, but they're reused
+    /// after an element is removed.
+    pub fn index(&self) -> usize {
+        self.ix
+    }
 }
 
 impl<T> Copy for ArenaId<T> {}
 impl<T> Clone for ArenaId<T> {
     fn clone(&self) -> Self {
-        ArenaId {
-            ix: self.ix,
-            tag: self.tag,
-        }
+        *self
     }
 }
 
@@ -139,6 +242,17 @@ impl<T> PartialEq for ArenaId<T> {
     }
 }
 
+impl<T> PartialOrd for ArenaId<T> {
+    fn partial_cmp(&self, rhs: &ArenaId<T>) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+impl<T> Ord for ArenaId<T> {
+    fn cmp(&self, rhs: &ArenaId<T>) -> std::cmp::Ordering {
+        self.ix.cmp(&rhs.ix)
+    }
+}
+
 impl<T> Hash for ArenaId<T> {
     fn hash<H>(&self, state: &mut H)
     where
@@ -174,9 +288,41 @@ mod tests {
         assert_eq!(arena[id0], 69);
     }
 
+    #[test]
+    fn test_from_raw() {
+        let mut arena: Arena<i32> = Arena::new();
+        let ids = (0..5).map(|i| arena.push(i)).collect::<Vec<_>>();
+        arena.remove(ids[1]);
+        arena.remove(ids[3]);
+
+        let (data, first_free) = arena.raw();
+        let data = data
+            .iter()
+            .map(|n| match n {
+                Node::Free { next_free } => Node::Free {
+                    next_free: *next_free,
+                },
+                Node::Occupied(v) => Node::Occupied(*v),
+            })
+            .collect::<Vec<_>>();
+        let mut copy = Arena::from_raw(data, first_free).unwrap();
+        assert_eq!(copy.push(7), arena.push(7));
+        assert_eq!(copy.push(8), arena.push(8));
+        assert_eq!(copy.push(9), arena.push(9));
+
+        // free lists that are cyclic, that skip free slots or that refer to occupied ones
+        let free = |next_free| Node::Free { next_free };
+        let from_raw = Arena::<u32>::from_raw;
+        assert!(from_raw(vec![free(Some(0))], Some(0)).is_none());
+        assert!(from_raw(vec![free(None), free(None)], Some(0)).is_none());
+        assert!(from_raw(vec![Node::Occupied(1)], Some(0)).is_none());
+        assert!(from_raw(vec![free(None)], Some(1)).is_none());
+        assert!(from_raw(vec![free(None), Node::Occupied(1)], Some(0)).is_some());
+    }
+
     #[test]
     fn test_iterators() {
-        let mut arena = Arena::new();
+        let mut arena: Arena<i32> = Arena::new();
         arena.push(42);
         arena.push(73);
         arena.push(0);
@@ -187,5 +333,4 @@ mod tests {
             assert_eq!(&arena[i], e);
         }
     }
-
 }
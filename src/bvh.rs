@@ -1,27 +1,27 @@
-use crate::geo::{Bbox, Vec2};
+use crate::geo::{Bbox, Scalar, Vec2};
 
 const LEAF_SIZE: usize = 128;
 const MIN_BBOX_AREA: f64 = 1e0;
 
 #[derive(Debug)]
-pub struct Bvh<Elem> {
-    root: BvhNode<Elem>,
+pub struct Bvh<Elem, T = f64> {
+    root: BvhNode<Elem, T>,
 }
 
 #[derive(Debug)]
-pub enum BvhNode<Elem> {
+pub enum BvhNode<Elem, T = f64> {
     Leaf {
-        elems: Vec<(Elem, Bbox)>,
-        bbox: Bbox,
+        elems: Vec<(Elem, Bbox<T>)>,
+        bbox: Bbox<T>,
     },
     Branch {
-        bbox: Bbox,
-        children: Box<[BvhNode<Elem>; 4]>,
+        bbox: Bbox<T>,
+        children: Box<[BvhNode<Elem, T>; 4]>,
     },
 }
 
-impl<Elem: Copy> Bvh<Elem> {
-    pub fn new(bbox: Bbox) -> Self {
+impl<Elem: Copy, T: Scalar> Bvh<Elem, T> {
+    pub fn new(bbox: Bbox<T>) -> Self {
         Bvh {
             root: BvhNode::Leaf {
                 elems: Vec::with_capacity(LEAF_SIZE),
@@ -40,30 +40,47 @@ impl<Elem: Copy> Bvh<Elem> {
         self.root.len()
     }
 
-    pub fn insert(&mut self, e: Elem, bbox: Bbox) {
+    /// Whether the Bvh contains no elements. _Not_ O(1).
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    pub fn insert(&mut self, e: Elem, bbox: Bbox<T>) {
         self.root.insert(e, bbox);
     }
 
-    pub fn remove(&mut self, e: &Elem, bbox: Bbox)
+    pub fn remove(&mut self, e: &Elem, bbox: Bbox<T>)
     where
         Elem: Eq,
     {
         self.root.remove(e, bbox)
     }
 
+    /// Iterate over all the elements in the Bvh along with their bboxes. Might return the same
+    /// element multiple times.
+    pub fn iter(&self) -> impl Iterator<Item = &(Elem, Bbox<T>)> {
+        self.root.iter()
+    }
+
     /// Return all the elements that contain the given refpoint. Might return the same elemnt
     /// multiple times.
     pub fn enclosing(
         &self,
-        refpoint: Vec2,
-        contains: impl Fn(&Elem, Vec2) -> bool,
+        refpoint: Vec2<T>,
+        contains: impl Fn(&Elem, Vec2<T>) -> bool,
     ) -> impl Iterator<Item = &Elem> {
         self.root.enclosing(refpoint, contains)
     }
+
+    /// Return all the elements whose bbox intersects the given one. Might return the same element
+    /// multiple times.
+    pub fn intersecting(&self, bbox: Bbox<T>) -> impl Iterator<Item = &Elem> {
+        self.root.intersecting(bbox)
+    }
 }
 
-impl<Elem: Copy> BvhNode<Elem> {
-    fn split(elems: &mut Vec<(Elem, Bbox)>, bbox: &mut Bbox) -> Self {
+impl<Elem: Copy, T: Scalar> BvhNode<Elem, T> {
+    fn split(elems: &mut Vec<(Elem, Bbox<T>)>, bbox: &mut Bbox<T>) -> Self {
         let pivot = bbox.center();
         let quads = bbox.split(pivot);
 
@@ -100,12 +117,12 @@ impl<Elem: Copy> BvhNode<Elem> {
         }
     }
 
-    pub fn insert(&mut self, e: Elem, e_bbox: Bbox) {
+    pub fn insert(&mut self, e: Elem, e_bbox: Bbox<T>) {
         match self {
             BvhNode::Leaf { elems, bbox } => {
                 elems.push((e, e_bbox));
 
-                if elems.len() > LEAF_SIZE && bbox.area() > MIN_BBOX_AREA {
+                if elems.len() > LEAF_SIZE && bbox.area() > T::from_f64(MIN_BBOX_AREA) {
                     *self = BvhNode::split(elems, bbox);
                 }
             }
@@ -119,7 +136,7 @@ impl<Elem: Copy> BvhNode<Elem> {
         }
     }
 
-    pub fn remove(&mut self, e: &Elem, bbox: Bbox)
+    pub fn remove(&mut self, e: &Elem, bbox: Bbox<T>)
     where
         Elem: Eq,
     {
@@ -139,8 +156,8 @@ impl<Elem: Copy> BvhNode<Elem> {
 
     pub fn enclosing(
         &self,
-        query_point: Vec2,
-        contains: impl Fn(&Elem, Vec2) -> bool,
+        query_point: Vec2<T>,
+        contains: impl Fn(&Elem, Vec2<T>) -> bool,
     ) -> impl Iterator<Item = &Elem> {
         let mut nodes = vec![self];
         let mut cur_elems = [].iter();
@@ -163,12 +180,78 @@ impl<Elem: Copy> BvhNode<Elem> {
         })
     }
 
-    fn intersects(&self, e_bbox: Bbox) -> bool {
-        let bbox = match self {
-            BvhNode::Branch { bbox, .. } | BvhNode::Leaf { bbox, .. } => bbox,
+    pub fn intersecting(&self, query_bbox: Bbox<T>) -> impl Iterator<Item = &Elem> {
+        let root = self.bbox();
+
+        // the bboxes of the elements can stick out of the root, hence the sides of the nodes that
+        // lie on the border of the root are considered unbounded
+        let unbounded = move |bbox: Bbox<T>| {
+            let (mut min, mut max) = (bbox.min(), bbox.max());
+            if min.x <= root.min().x {
+                min.x = T::neg_infinity();
+            }
+            if min.y <= root.min().y {
+                min.y = T::neg_infinity();
+            }
+            if max.x >= root.max().x {
+                max.x = T::infinity();
+            }
+            if max.y >= root.max().y {
+                max.y = T::infinity();
+            }
+
+            let mut unbounded = Bbox::new(min);
+            unbounded.expand(max);
+            unbounded
         };
 
-        bbox.intersection(e_bbox).is_some()
+        let mut nodes = vec![self];
+        let mut cur_elems: std::slice::Iter<(Elem, Bbox<T>)> = [].iter();
+
+        std::iter::from_fn(move || loop {
+            for (e, e_bbox) in cur_elems.by_ref() {
+                if e_bbox.intersection(query_bbox).is_some() {
+                    return Some(e);
+                }
+            }
+
+            match nodes.pop()? {
+                BvhNode::Leaf { elems, .. } => cur_elems = elems.iter(),
+                BvhNode::Branch { children, .. } => {
+                    nodes.extend(
+                        children
+                            .iter()
+                            .filter(|c| unbounded(c.bbox()).intersection(query_bbox).is_some()),
+                    );
+                }
+            }
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Elem, Bbox<T>)> {
+        let mut nodes = vec![self];
+        let mut cur_elems = [].iter();
+
+        std::iter::from_fn(move || loop {
+            if let Some(e) = cur_elems.next() {
+                return Some(e);
+            }
+
+            match nodes.pop()? {
+                BvhNode::Leaf { elems, .. } => cur_elems = elems.iter(),
+                BvhNode::Branch { children, .. } => nodes.extend(children.iter()),
+            }
+        })
+    }
+
+    fn intersects(&self, e_bbox: Bbox<T>) -> bool {
+        self.bbox().intersection(e_bbox).is_some()
+    }
+
+    fn bbox(&self) -> Bbox<T> {
+        match self {
+            BvhNode::Branch { bbox, .. } | BvhNode::Leaf { bbox, .. } => *bbox,
+        }
     }
 
     pub fn depth(&self) -> usize {
@@ -186,6 +269,13 @@ impl<Elem: Copy> BvhNode<Elem> {
             BvhNode::Branch { children, .. } => children.iter().map(BvhNode::len).sum(),
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            BvhNode::Leaf { elems, .. } => elems.is_empty(),
+            BvhNode::Branch { children, .. } => children.iter().all(BvhNode::is_empty),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -281,7 +371,22 @@ mod tests {
                     .collect::<HashSet<_>>();
 
                 prop_assert_eq!(enclosing, brute_force_enclosing);
+
+                b.enlarge(100.0);
+                let intersecting = bvh.intersecting(b).collect::<HashSet<_>>();
+                let brute_force_intersecting = pts
+                    .iter()
+                    .filter(|&&(x, y)| {
+                        (f64::from(x) - pf.x).abs() <= 110.0 && (f64::from(y) - pf.y).abs() <= 110.0
+                    })
+                    .collect::<HashSet<_>>();
+                prop_assert_eq!(intersecting, brute_force_intersecting);
             }
+
+            prop_assert_eq!(
+                bvh.iter().map(|(e, _)| e).collect::<HashSet<_>>(),
+                pts.iter().collect::<HashSet<_>>()
+            );
         }
 
     }
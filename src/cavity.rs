@@ -0,0 +1,71 @@
+//! The cavity search of the Bowyer-Watson insertion shared by `DelaunayMesh` and
+//! `SphericalDelaunayMesh`. Both store their triangles as counter-clockwise triples of vertex ids
+//! and map each directed edge to the triangle it belongs to.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// The connected region of triangles that contains `start` and whose other triangles are all in
+/// conflict with the new point according to the given predicate. The region is found by visiting
+/// the neighbors across the edges, triangles on the boundary of the mesh have no neighbor there.
+pub(crate) fn conflict_region<V, T>(
+    start: T,
+    edges: &HashMap<(V, V), T>,
+    vertices: impl Fn(T) -> [V; 3],
+    in_conflict: impl Fn(T) -> bool,
+) -> HashSet<T>
+where
+    V: Copy + Eq + Hash,
+    T: Copy + Eq + Hash,
+{
+    let mut region = HashSet::new();
+    region.insert(start);
+
+    let mut stack = vec![start];
+    while let Some(tid) = stack.pop() {
+        let [va, vb, vc] = vertices(tid);
+
+        for &(v0, v1) in &[(va, vb), (vb, vc), (vc, va)] {
+            let neighbor = match edges.get(&(v1, v0)) {
+                Some(n) => *n,
+                None => continue,
+            };
+
+            if !region.contains(&neighbor) && in_conflict(neighbor) {
+                region.insert(neighbor);
+                stack.push(neighbor);
+            }
+        }
+    }
+
+    region
+}
+
+/// The edges of the region whose twin doesn't belong to the region. They keep their direction,
+/// therefore connecting them to a point inside the region yields counter-clockwise triangles.
+pub(crate) fn region_boundary<V, T>(
+    region: &HashSet<T>,
+    edges: &HashMap<(V, V), T>,
+    vertices: impl Fn(T) -> [V; 3],
+) -> Vec<(V, V)>
+where
+    V: Copy + Eq + Hash,
+    T: Copy + Eq + Hash,
+{
+    let mut boundary = vec![];
+
+    for &tid in region {
+        let [va, vb, vc] = vertices(tid);
+
+        for &(v0, v1) in &[(va, vb), (vb, vc), (vc, va)] {
+            let inner = edges
+                .get(&(v1, v0))
+                .is_some_and(|twin| region.contains(twin));
+            if !inner {
+                boundary.push((v0, v1));
+            }
+        }
+    }
+
+    boundary
+}
@@ -0,0 +1,446 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::geo::{polygon_area, polygon_contains, Polygon, Vec2};
+use crate::mesh::{DelaunayMesh, VertexId};
+
+// undirected edge of the mesh, the lowest vertex is always first.
+type EdgeKey = (VertexId, VertexId);
+
+// a point on the boundary of an isoband, identified symbolically so that the pieces of
+// neighboring triangles share exactly the same points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BandPoint {
+    Vertex(VertexId),
+
+    // the point along the edge where the field is equal to either the lower (false) or the upper
+    // (true) level of the band.
+    Crossing(EdgeKey, bool),
+}
+
+impl DelaunayMesh {
+    /// Extract the contour lines of the scalar field defined at the vertices of the mesh, that is
+    /// the lines where the linear interpolation of the field over the triangles is equal to each
+    /// of the given levels. The result contains the polylines of each level in the same order as
+    /// `levels`.
+    ///
+    /// Polylines are oriented so that the field is higher on their left. Closed polylines end
+    /// with their first point, open ones start and end on the boundary of the triangulated area.
+    /// Vertices whose value is exactly equal to a level are considered above it.
+    pub fn contours(&self, field: impl Fn(VertexId) -> f64, levels: &[f64]) -> Vec<Vec<Vec<Vec2>>> {
+        let values = self
+            .vertices()
+            .map(|(vid, _)| (vid, field(vid)))
+            .collect::<HashMap<_, _>>();
+
+        levels
+            .iter()
+            .map(|&level| self.contour_level(&values, level))
+            .collect()
+    }
+
+    /// Extract the isobands of the scalar field defined at the vertices of the mesh, that is the
+    /// regions where the linear interpolation of the field over the triangles lies between two
+    /// consecutive levels. The result contains the polygons of the band between `levels[i]`
+    /// (inclusive) and `levels[i + 1]` (exclusive) at index `i`, the levels must be sorted.
+    ///
+    /// Each triangle is clipped against the band and the pieces are then merged along the edges
+    /// they share, therefore the bands of a field tile the triangulated area without gaps.
+    pub fn isobands(&self, field: impl Fn(VertexId) -> f64, levels: &[f64]) -> Vec<Vec<Polygon>> {
+        let values = self
+            .vertices()
+            .map(|(vid, _)| (vid, field(vid)))
+            .collect::<HashMap<_, _>>();
+
+        levels
+            .windows(2)
+            .map(|w| self.isoband(&values, w[0], w[1]))
+            .collect()
+    }
+
+    fn isoband(&self, values: &HashMap<VertexId, f64>, lower: f64, upper: f64) -> Vec<Polygon> {
+        let level = |upper_level: bool| if upper_level { upper } else { lower };
+
+        let value = |p: BandPoint| match p {
+            BandPoint::Vertex(v) => values[&v],
+            BandPoint::Crossing(_, upper_level) => level(upper_level),
+        };
+
+        let position = |p: BandPoint| match p {
+            BandPoint::Vertex(v) => self.vertex(v).position(),
+            BandPoint::Crossing(e, upper_level) => self.crossing(values, e, level(upper_level)),
+        };
+
+        // clip the polygon keeping either the part above or below the given level, the clipped
+        // sides always lie on an edge of the mesh
+        let clip = |polygon: Vec<BandPoint>, upper_level: bool| {
+            let level = level(upper_level);
+            let inside = |p: BandPoint| (value(p) >= level) != upper_level;
+
+            let mut out: Vec<BandPoint> = Vec::with_capacity(polygon.len() + 1);
+            let mut push = |p: BandPoint| {
+                if out.last() != Some(&p) {
+                    out.push(p);
+                }
+            };
+
+            for (i, &cur) in polygon.iter().enumerate() {
+                let prev = polygon[(i + polygon.len() - 1) % polygon.len()];
+
+                if inside(prev) != inside(cur) {
+                    let (v0, v1) = match (prev, cur) {
+                        (BandPoint::Crossing(e, _), _) | (_, BandPoint::Crossing(e, _)) => e,
+                        (BandPoint::Vertex(a), BandPoint::Vertex(b)) => (a.min(b), a.max(b)),
+                    };
+
+                    if values[&v0] == level {
+                        push(BandPoint::Vertex(v0));
+                    } else if values[&v1] == level {
+                        push(BandPoint::Vertex(v1));
+                    } else {
+                        push(BandPoint::Crossing((v0, v1), upper_level));
+                    }
+                }
+                if inside(cur) {
+                    push(cur);
+                }
+            }
+
+            if out.len() > 1 && out.first() == out.last() {
+                out.pop();
+            }
+            out
+        };
+
+        // the sides shared by two pieces cancel out, the remaining ones form the boundary of the
+        // band with the exterior rings counter-clockwise and the holes clockwise
+        let mut sides = vec![];
+        let mut alive = HashMap::<_, Vec<usize>>::new();
+        for (_, t) in self.triangles() {
+            let piece = t.vertices().iter().map(|v| BandPoint::Vertex(*v)).collect();
+            let piece = clip(clip(piece, false), true);
+            if piece.len() < 3 {
+                continue;
+            }
+
+            for (i, &p) in piece.iter().enumerate() {
+                let q = piece[(i + 1) % piece.len()];
+
+                match alive.get_mut(&(q, p)).and_then(|twins| twins.pop()) {
+                    Some(twin) => sides[twin] = None,
+                    None => {
+                        alive.entry((p, q)).or_default().push(sides.len());
+                        sides.push(Some((p, q)));
+                    }
+                }
+            }
+        }
+
+        let mut outgoing = HashMap::<_, Vec<BandPoint>>::new();
+        for &(p, q) in sides.iter().flatten() {
+            outgoing.entry(p).or_default().push(q);
+        }
+
+        // clockwise angle to go from direction d0 to direction d1 in (0, 2pi]
+        let cw_angle = |d0: Vec2, d1: Vec2| {
+            let a = (d0.y.atan2(d0.x) - d1.y.atan2(d1.x)).rem_euclid(2.0 * std::f64::consts::PI);
+            if a == 0.0 {
+                2.0 * std::f64::consts::PI
+            } else {
+                a
+            }
+        };
+
+        let mut exteriors = vec![];
+        let mut holes = vec![];
+        for &(start, first) in sides.iter().flatten() {
+            let firsts = outgoing.get_mut(&start).unwrap();
+            match firsts.iter().position(|q| *q == first) {
+                Some(i) => firsts.remove(i),
+                None => continue,
+            };
+
+            let mut ring = vec![start];
+            let (mut prev, mut cur) = (start, first);
+            while cur != start {
+                ring.push(cur);
+
+                // when the band touches itself at a vertex take the first side clockwise from the
+                // incoming one, so that the ring only turns around the region it bounds
+                let (p, back) = (position(cur), position(prev) - position(cur));
+                let nexts = outgoing.get_mut(&cur).unwrap();
+                let i = (0..nexts.len())
+                    .min_by(|&i, &j| {
+                        let ai = cw_angle(back, position(nexts[i]) - p);
+                        let aj = cw_angle(back, position(nexts[j]) - p);
+                        ai.partial_cmp(&aj).unwrap()
+                    })
+                    .unwrap();
+
+                prev = cur;
+                cur = nexts.remove(i);
+            }
+
+            // the ring can still pass through a touching vertex more than once, e.g. when a hole
+            // touches an island inside it, split it there into simple rings so that each one is
+            // classified on its own
+            let mut stack = vec![];
+            let mut simple_rings = vec![];
+            for p in ring {
+                if let Some(i) = stack.iter().position(|q| *q == p) {
+                    simple_rings.push(stack.split_off(i));
+                }
+                stack.push(p);
+            }
+            simple_rings.push(stack);
+
+            for ring in simple_rings {
+                let ring = ring.into_iter().map(position).collect::<Vec<_>>();
+                let area = polygon_area(&ring);
+                if area > 0.0 {
+                    exteriors.push((area, ring));
+                } else if area < 0.0 {
+                    holes.push(ring);
+                }
+            }
+        }
+
+        let mut polygons = exteriors
+            .iter()
+            .map(|(_, ring)| Polygon {
+                exterior: ring.clone(),
+                holes: vec![],
+            })
+            .collect::<Vec<_>>();
+
+        // each hole belongs to the smallest exterior ring that contains it
+        for hole in holes {
+            let p = (hole[0] + hole[1]) / 2.0;
+            let owner = exteriors
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, ring))| polygon_contains(ring, p))
+                .min_by(|(_, (a, _)), (_, (b, _))| a.partial_cmp(b).unwrap())
+                .map(|(i, _)| i);
+
+            if let Some(i) = owner {
+                polygons[i].holes.push(hole);
+            }
+        }
+
+        polygons
+    }
+
+    // the point along the edge where the field is equal to the given level.
+    fn crossing(&self, values: &HashMap<VertexId, f64>, (v0, v1): EdgeKey, level: f64) -> Vec2 {
+        let (p0, p1) = (self.vertex(v0).position(), self.vertex(v1).position());
+        let (f0, f1) = (values[&v0], values[&v1]);
+        p0 + (p1 - p0) * ((level - f0) / (f1 - f0))
+    }
+
+    fn contour_level(&self, values: &HashMap<VertexId, f64>, level: f64) -> Vec<Vec<Vec2>> {
+        let above = |v: VertexId| values[&v] >= level;
+        let crossing = |e: EdgeKey| self.crossing(values, e, level);
+
+        // each triangle crossed by the level contributes a segment going from the edge where the
+        // field goes down to the edge where it goes up when walking counter-clockwise, this keeps
+        // the higher values on the left
+        let mut segments = vec![];
+        for (_, t) in self.triangles() {
+            let [a, b, c] = t.vertices();
+
+            let mut down = None;
+            let mut up = None;
+            for &(v0, v1) in &[(a, b), (b, c), (c, a)] {
+                let key = (v0.min(v1), v0.max(v1));
+                match (above(v0), above(v1)) {
+                    (true, false) => down = Some(key),
+                    (false, true) => up = Some(key),
+                    _ => {}
+                }
+            }
+
+            if let (Some(down), Some(up)) = (down, up) {
+                segments.push((down, up));
+            }
+        }
+
+        let next = segments.iter().cloned().collect::<HashMap<_, _>>();
+        let ends = segments.iter().map(|(_, e)| *e).collect::<HashSet<_>>();
+
+        // open polylines start at edges that don't end any segment, the remaining ones are closed
+        let starts = segments
+            .iter()
+            .filter(|(s, _)| !ends.contains(s))
+            .chain(segments.iter())
+            .map(|(s, _)| *s)
+            .collect::<Vec<_>>();
+
+        let mut visited = HashSet::new();
+        let mut polylines = vec![];
+        for start in starts {
+            if visited.contains(&start) {
+                continue;
+            }
+
+            let mut polyline: Vec<Vec2> = vec![crossing(start)];
+            let mut edge = start;
+            while let Some(&e) = next.get(&edge) {
+                if !visited.insert(edge) {
+                    break;
+                }
+
+                let p = crossing(e);
+                if polyline.last() != Some(&p) {
+                    polyline.push(p);
+                }
+                edge = e;
+            }
+
+            // contours passing exactly through a vertex can collapse to a single point
+            if polyline.len() > 1 {
+                polylines.push(polyline);
+            }
+        }
+
+        polylines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    use crate::geo::Bbox;
+
+    #[test]
+    fn test_closed_contour_around_peak() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(4.0, 4.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for y in 0..=4 {
+            for x in 0..=4 {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+        }
+        let peak = mesh.find_vertex(Vec2::new(2.0, 2.0)).unwrap();
+
+        let contours = mesh.contours(|v| if v == peak { 1.0 } else { 0.0 }, &[0.5, 2.0]);
+        assert_eq!(contours.len(), 2);
+        assert!(contours[1].is_empty());
+
+        assert_eq!(contours[0].len(), 1);
+        let polyline = &contours[0][0];
+        assert_eq!(polyline.first(), polyline.last());
+        assert_eq!(polyline.len(), mesh.vertex_triangles(peak).len() + 1);
+        for p in polyline {
+            let d = p.dist(Vec2::new(2.0, 2.0));
+            assert!(d >= 0.5 && d <= 0.5 * 2.0_f64.sqrt() + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_isobands_around_peak() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(4.0, 4.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for y in 0..=4 {
+            for x in 0..=4 {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+        }
+        let peak = mesh.find_vertex(Vec2::new(2.0, 2.0)).unwrap();
+
+        let bands = mesh.isobands(
+            |v| if v == peak { 1.0 } else { 0.0 },
+            &[-1.0, 0.25, 0.75, 2.0],
+        );
+        assert_eq!(bands.len(), 3);
+
+        // the outer band is the whole square except the region around the peak, the middle one is
+        // a ring and the inner one has no holes
+        let holes = bands
+            .iter()
+            .map(|b| b.iter().map(|p| p.holes.len()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(holes, vec![vec![1], vec![1], vec![0]]);
+
+        let area = bands.iter().flatten().map(|p| p.area()).sum::<f64>();
+        assert!((area - 16.0).abs() < 1e-9);
+        assert!(bands[2][0].contains(Vec2::new(2.0, 2.0)));
+        assert!(!bands[1][0].contains(Vec2::new(2.0, 2.0)));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_isobands_tile_the_mesh(
+            pts in prop::collection::vec((0_u16..=100, 0_u16..=100), 3..50),
+            values in prop::collection::vec(0_u8..=10, 50),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(100.0, 100.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            for (x, y) in pts {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+
+            let field = |v: VertexId| {
+                let p = mesh.vertex(v).position();
+                f64::from(values[(p.x * 7.0 + p.y) as usize % values.len()])
+            };
+            let bands = mesh.isobands(field, &[0.0, 2.5, 5.0, 7.0, 11.0]);
+
+            let area = mesh
+                .triangles()
+                .map(|(tid, _)| polygon_area(&mesh.triangle_vertices(tid)))
+                .sum::<f64>();
+            let bands_area = bands.iter().flatten().map(|p| p.area()).sum::<f64>();
+            prop_assert!((area - bands_area).abs() < 1e-6 * area.max(1.0));
+
+            for polygon in bands.iter().flatten() {
+                prop_assert!(polygon_area(&polygon.exterior) > 0.0);
+                prop_assert!(polygon.holes.iter().all(|h| polygon_area(h) < 0.0));
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_linear_field_contours(
+            pts in prop::collection::vec((0_u16..=100, 0_u16..=100), 3..50),
+            levels in prop::collection::vec(0_u16..=200, 1..5),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(100.0, 100.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            for (x, y) in pts {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+
+            let field = |p: Vec2| p.x + p.y;
+            let levels = levels.into_iter().map(|l| f64::from(l) + 0.5).collect::<Vec<_>>();
+            let contours = mesh.contours(|v| field(mesh.vertex(v).position()), &levels);
+
+            for (level, polylines) in levels.iter().zip(contours) {
+                for polyline in polylines {
+                    prop_assert!(polyline.len() >= 2);
+
+                    for p in &polyline {
+                        prop_assert!((field(*p) - level).abs() < 1e-9);
+                    }
+
+                    // the gradient (1, 1) must be on the left
+                    for w in polyline.windows(2) {
+                        let d = w[1] - w[0];
+                        prop_assert!(d.x - d.y > 0.0);
+                    }
+                }
+            }
+        }
+    }
+}
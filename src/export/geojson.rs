@@ -0,0 +1,277 @@
+use std::io::{self, Write};
+
+use crate::export::Field;
+use crate::geo::Vec2;
+use crate::mesh::{DelaunayMesh, TriangleId, VertexId};
+
+/// Write the triangles of the mesh as a GeoJSON FeatureCollection of Polygons. Each feature has
+/// the index of the triangle as its `id` and the given properties, where vector fields are
+/// written as arrays of three numbers.
+///
+/// Rings are closed and counter-clockwise as required by RFC 7946, but the coordinates are
+/// written as they are without any projection. Features with non finite coordinates are skipped
+/// by all the writers, while non finite property values are written as null.
+pub fn write_triangles(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    properties: &[Field<TriangleId>],
+) -> io::Result<()> {
+    write_feature_collection(
+        out,
+        mesh.triangles().map(|(tid, _)| {
+            let ring = mesh.triangle_vertices(tid).to_vec();
+            let props = properties.iter().map(|p| property(p, tid)).collect();
+            (Some(tid.index()), Geometry::Polygon(ring), props)
+        }),
+    )
+}
+
+/// Write the Voronoi cells (or power cells) of the vertices of the mesh clipped to its bbox as a
+/// GeoJSON FeatureCollection of Polygons. Each feature has the index of the vertex as its `id`
+/// and the given properties.
+pub fn write_voronoi_cells(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    properties: &[Field<VertexId>],
+) -> io::Result<()> {
+    let bbox = mesh.bbox();
+
+    write_feature_collection(
+        out,
+        mesh.power_diagram().filter_map(|(vid, cell)| {
+            let ring = bbox.clip_polygon(&cell);
+            if ring.len() < 3 {
+                return None;
+            }
+
+            let props = properties.iter().map(|p| property(p, vid)).collect();
+            Some((Some(vid.index()), Geometry::Polygon(ring), props))
+        }),
+    )
+}
+
+/// Write the edges of the mesh as a GeoJSON FeatureCollection of LineStrings, one per edge, with
+/// the given properties. The edges are oriented like in `DelaunayMesh::edges` and since they
+/// don't have an id of their own the features don't have one either.
+pub fn write_edges(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    properties: &[Field<(VertexId, VertexId)>],
+) -> io::Result<()> {
+    write_feature_collection(
+        out,
+        mesh.edges().map(|(a, b)| {
+            let line = vec![mesh.vertex(a).position(), mesh.vertex(b).position()];
+            let props = properties.iter().map(|p| property(p, (a, b))).collect();
+            (None, Geometry::LineString(line), props)
+        }),
+    )
+}
+
+enum Geometry {
+    // the ring is closed when written
+    Polygon(Vec<Vec2>),
+    LineString(Vec<Vec2>),
+}
+
+impl Geometry {
+    fn is_finite(&self) -> bool {
+        let points = match self {
+            Geometry::Polygon(points) | Geometry::LineString(points) => points,
+        };
+
+        points.iter().all(|p| p.x.is_finite() && p.y.is_finite())
+    }
+}
+
+// the name of the field along with its value as JSON
+fn property<'a, Id>(field: &Field<'a, Id>, id: Id) -> (&'a str, String) {
+    let value = match field {
+        Field::Scalar(_, f) => number(f(id)),
+        Field::Vector(_, f) => {
+            let v = f(id);
+            format!("[{},{},{}]", number(v.x), number(v.y), number(v.z))
+        }
+    };
+
+    (field.name(), value)
+}
+
+fn write_feature_collection<'a>(
+    out: &mut impl Write,
+    features: impl Iterator<Item = (Option<usize>, Geometry, Vec<(&'a str, String)>)>,
+) -> io::Result<()> {
+    write!(out, r#"{{"type":"FeatureCollection","features":["#)?;
+
+    // JSON has no representation for non finite coordinates and null is not a valid one either
+    let features = features.filter(|(_, geometry, _)| geometry.is_finite());
+    for (i, (id, geometry, properties)) in features.enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+
+        write!(out, r#"{{"type":"Feature","#)?;
+        if let Some(id) = id {
+            write!(out, r#""id":{},"#, id)?;
+        }
+
+        let (points, closed) = match &geometry {
+            Geometry::Polygon(ring) => {
+                write!(out, r#""geometry":{{"type":"Polygon","coordinates":[["#)?;
+                (ring, true)
+            }
+            Geometry::LineString(line) => {
+                write!(out, r#""geometry":{{"type":"LineString","coordinates":["#)?;
+                (line, false)
+            }
+        };
+        let first = if closed { points.first() } else { None };
+        for (j, p) in points.iter().chain(first).enumerate() {
+            if j > 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "[{},{}]", p.x, p.y)?;
+        }
+        if closed {
+            write!(out, "]")?;
+        }
+
+        write!(out, r#"]}},"properties":{{"#)?;
+        for (j, (name, value)) in properties.iter().enumerate() {
+            if j > 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{}:{}", string(name), value)?;
+        }
+        write!(out, "}}}}")?;
+    }
+
+    writeln!(out, "]}}")
+}
+
+// JSON has no representation for non finite numbers, null is the usual replacement.
+fn number(v: f64) -> String {
+    if v.is_finite() {
+        v.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::geo::{Bbox, Vec3};
+
+    #[test]
+    fn test_geojson() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let mut out = vec![];
+        write_triangles(&mut out, &mesh, &[]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"type\":\"FeatureCollection\",\"features\":[]}\n"
+        );
+
+        for &(x, y) in &[(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)] {
+            mesh.insert(Vec2::new(x, y));
+        }
+        let (tid, _) = mesh.triangles().next().unwrap();
+
+        let mut out = vec![];
+        write_triangles(
+            &mut out,
+            &mesh,
+            &[
+                Field::scalar("area", |_| 50.0),
+                Field::scalar("\"bad\"", |_| f64::NAN),
+                Field::vector("normal", |_| Vec3::new(0.0, 0.0, 1.0)),
+            ],
+        )
+        .unwrap();
+        let json = String::from_utf8(out).unwrap();
+
+        assert!(json.starts_with(r#"{"type":"FeatureCollection","features":[{"type":"Feature","#));
+        assert!(json.contains(&format!(r#""id":{},"#, tid.index())));
+        assert!(json.contains(r#""properties":{"area":50,"\"bad\"":null,"normal":[0,0,1]}"#));
+        assert_eq!(json.matches(r#""type":"Polygon""#).count(), 1);
+
+        let [a, _, _] = mesh.triangle_vertices(tid);
+        let first = format!("[{},{}]", a.x, a.y);
+        assert_eq!(json.matches(&first).count(), 2);
+
+        let mut out = vec![];
+        write_voronoi_cells(
+            &mut out,
+            &mesh,
+            &[Field::scalar("x", |v| mesh.vertex(v).position().x)],
+        )
+        .unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert_eq!(json.matches(r#""type":"Polygon""#).count(), 3);
+        assert!(json.contains(r#""properties":{"x":10}"#));
+
+        let mut out = vec![];
+        write_edges(
+            &mut out,
+            &mesh,
+            &[Field::scalar("length", |(a, b)| {
+                mesh.vertex(a).position().dist(mesh.vertex(b).position())
+            })],
+        )
+        .unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert_eq!(json.matches(r#""type":"LineString""#).count(), 3);
+        assert!(!json.contains(r#""id""#));
+        assert!(json.contains(r#""coordinates":[[0,0],[10,0]]"#));
+        assert_eq!(json.matches(r#""length":10}"#).count(), 2);
+    }
+
+    #[test]
+    fn test_geojson_skips_non_finite_features() {
+        let line = |x: f64| Geometry::LineString(vec![Vec2::new(x, 0.0), Vec2::new(1.0, 0.0)]);
+
+        let mut out = vec![];
+        write_feature_collection(
+            &mut out,
+            vec![
+                (Some(0), line(f64::NAN), vec![]),
+                (Some(1), line(0.0), vec![]),
+                (Some(2), line(f64::NEG_INFINITY), vec![]),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        let json = String::from_utf8(out).unwrap();
+
+        assert!(!json.contains("null"));
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"type":"FeatureCollection","features":[{"type":"Feature","id":1,"#,
+                r#""geometry":{"type":"LineString","coordinates":[[0,0],[1,0]]},"#,
+                r#""properties":{}}]}"#,
+                "\n"
+            )
+        );
+    }
+}
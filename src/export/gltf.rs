@@ -0,0 +1,337 @@
+//! Binary glTF 2.0 (`.glb`) files with a single mesh made of one triangle list primitive.
+
+use std::io::{self, Write};
+
+use crate::export::compact_vertices;
+use crate::geo::Vec3;
+use crate::mesh::{DelaunayMesh, VertexId};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Render a `DelaunayMesh` as a self-contained binary glTF file with positions, smooth normals,
+/// indices and optionally vertex colors as RGBA bytes.
+///
+/// By default the mesh is flat, the heights and the colors are opt-in:
+///
+/// ```
+/// # use delaunay_mesh::geo::{Bbox, Vec2};
+/// # use delaunay_mesh::DelaunayMesh;
+/// use delaunay_mesh::export::gltf::Glb;
+///
+/// let mut bbox = Bbox::new(Vec2::zero());
+/// bbox.expand(Vec2::new(10.0, 10.0));
+/// let mut mesh = DelaunayMesh::new(bbox);
+/// mesh.insert(Vec2::new(5.0, 5.0));
+///
+/// let mut out = vec![];
+/// Glb::new(&mesh)
+///     .z(|v| mesh.vertex(v).position().x / 10.0)
+///     .colors(|_| [255, 0, 0, 255])
+///     .write(&mut out)
+///     .unwrap();
+/// ```
+///
+/// Since glTF is y-up the point `(x, y, z)` of the mesh is stored as `(x, z, -y)`, that is the
+/// mesh lies on the ground and z is the height. The normal of each vertex is the area weighted
+/// mean of the normals of the triangles around it.
+pub struct Glb<'a> {
+    mesh: &'a DelaunayMesh,
+    z: Box<dyn Fn(VertexId) -> f64 + 'a>,
+    colors: Option<Box<dyn Fn(VertexId) -> [u8; 4] + 'a>>,
+}
+
+impl<'a> Glb<'a> {
+    pub fn new(mesh: &'a DelaunayMesh) -> Self {
+        Glb {
+            mesh,
+            z: Box::new(|_| 0.0),
+            colors: None,
+        }
+    }
+
+    /// Use the value returned by the given callback as the z coordinate of each vertex.
+    pub fn z(mut self, z: impl Fn(VertexId) -> f64 + 'a) -> Self {
+        self.z = Box::new(z);
+        self
+    }
+
+    /// Add a `COLOR_0` attribute with the color returned by the given callback for each vertex.
+    pub fn colors(mut self, colors: impl Fn(VertexId) -> [u8; 4] + 'a) -> Self {
+        self.colors = Some(Box::new(colors));
+        self
+    }
+
+    /// Write the mesh, an error of kind `InvalidData` is returned if the z of any vertex is not
+    /// finite or doesn't fit in a `f32` since glTF requires finite positions.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let mesh = self.mesh;
+        let (vertices, indices) = compact_vertices(mesh);
+
+        let positions = vertices
+            .iter()
+            .map(|vid| {
+                let p = mesh.vertex(*vid).position();
+                // 0 - y rather than -y to avoid negative zeros in the bounds
+                let p = Vec3::new(p.x, (self.z)(*vid), 0.0 - p.y);
+                if [p.x, p.y, p.z].iter().all(|c| (*c as f32).is_finite()) {
+                    Ok(p)
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("non finite position for vertex {:?}", vid),
+                    ))
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let faces = mesh
+            .triangles()
+            .map(|(_, t)| {
+                let [a, b, c] = t.vertices();
+                [indices[&a], indices[&b], indices[&c]]
+            })
+            .collect::<Vec<_>>();
+
+        // the cross product is twice the area of the triangle, hence summing it weights the normals
+        // by area
+        let mut normals = vec![Vec3::zero(); positions.len()];
+        for [a, b, c] in &faces {
+            let (pa, pb, pc) = (positions[*a], positions[*b], positions[*c]);
+            let n = (pb - pa).cross(pc - pa);
+            for v in &[a, b, c] {
+                normals[**v] = normals[**v] + n;
+            }
+        }
+        for n in &mut normals {
+            *n = if n.norm2() > 0.0 {
+                n.normalized()
+            } else {
+                Vec3::new(0.0, 1.0, 0.0)
+            };
+        }
+
+        let mut bin = vec![];
+        let mut views = vec![];
+        let mut accessors = vec![];
+
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        let start = bin.len();
+        for p in &positions {
+            for (i, c) in [p.x, p.y, p.z].iter().enumerate() {
+                let c = *c as f32;
+                min[i] = min[i].min(c);
+                max[i] = max[i].max(c);
+                bin.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        let bounds = if positions.is_empty() {
+            String::new()
+        } else {
+            format!(
+                r#","min":[{},{},{}],"max":[{},{},{}]"#,
+                min[0], min[1], min[2], max[0], max[1], max[2]
+            )
+        };
+        views.push(buffer_view(start, bin.len(), ARRAY_BUFFER));
+        accessors.push(format!(
+            r#"{{"bufferView":0,"componentType":{},"count":{},"type":"VEC3"{}}}"#,
+            FLOAT,
+            positions.len(),
+            bounds
+        ));
+
+        let start = bin.len();
+        for n in &normals {
+            for c in &[n.x, n.y, n.z] {
+                bin.extend_from_slice(&(*c as f32).to_le_bytes());
+            }
+        }
+        views.push(buffer_view(start, bin.len(), ARRAY_BUFFER));
+        accessors.push(format!(
+            r#"{{"bufferView":1,"componentType":{},"count":{},"type":"VEC3"}}"#,
+            FLOAT,
+            normals.len()
+        ));
+
+        let start = bin.len();
+        for i in faces.iter().flat_map(|f| f.iter()) {
+            bin.extend_from_slice(&(*i as u32).to_le_bytes());
+        }
+        views.push(buffer_view(start, bin.len(), ELEMENT_ARRAY_BUFFER));
+        accessors.push(format!(
+            r#"{{"bufferView":2,"componentType":{},"count":{},"type":"SCALAR"}}"#,
+            UNSIGNED_INT,
+            faces.len() * 3
+        ));
+
+        let mut attributes = r#""POSITION":0,"NORMAL":1"#.to_string();
+        if let Some(colors) = &self.colors {
+            let start = bin.len();
+            for vid in &vertices {
+                bin.extend_from_slice(&colors(*vid));
+            }
+            views.push(buffer_view(start, bin.len(), ARRAY_BUFFER));
+            accessors.push(format!(
+                r#"{{"bufferView":3,"componentType":{},"normalized":true,"count":{},"type":"VEC4"}}"#,
+                UNSIGNED_BYTE,
+                vertices.len()
+            ));
+            attributes.push_str(r#","COLOR_0":3"#);
+        }
+
+        let mut json = format!(
+            concat!(
+                r#"{{"asset":{{"version":"2.0","generator":"delaunay-mesh"}},"#,
+                r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"#,
+                r#""meshes":[{{"primitives":[{{"attributes":{{{}}},"indices":2,"mode":4}}]}}],"#,
+                r#""buffers":[{{"byteLength":{}}}],"bufferViews":[{}],"accessors":[{}]}}"#,
+            ),
+            attributes,
+            bin.len(),
+            views.join(","),
+            accessors.join(","),
+        )
+        .into_bytes();
+
+        // chunks must be aligned to 4 bytes, the json with spaces and the binary data with zeros
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+
+        let total_len = 12 + 8 + json.len() + 8 + bin.len();
+        for word in &[GLB_MAGIC, GLB_VERSION, total_len as u32] {
+            out.write_all(&word.to_le_bytes())?;
+        }
+
+        out.write_all(&(json.len() as u32).to_le_bytes())?;
+        out.write_all(&CHUNK_JSON.to_le_bytes())?;
+        out.write_all(&json)?;
+
+        out.write_all(&(bin.len() as u32).to_le_bytes())?;
+        out.write_all(&CHUNK_BIN.to_le_bytes())?;
+        out.write_all(&bin)
+    }
+}
+
+fn buffer_view(start: usize, end: usize, target: u32) -> String {
+    format!(
+        r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+        start,
+        end - start,
+        target
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+
+    use crate::geo::{Bbox, Vec2};
+
+    fn u32_at(data: &[u8], i: usize) -> u32 {
+        u32::from_le_bytes(data[i..i + 4].try_into().unwrap())
+    }
+
+    fn f32s(data: &[u8]) -> Vec<f32> {
+        data.chunks(4)
+            .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_glb() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (4.0, 5.0),
+        ] {
+            mesh.insert(Vec2::new(x, y));
+        }
+
+        let mut glb = vec![];
+        Glb::new(&mesh).write(&mut glb).unwrap();
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(u32_at(&glb, 4), 2);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+        let json_len = u32_at(&glb, 12) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(json_len % 4, 0);
+        let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+        assert!(json.contains(r#""attributes":{"POSITION":0,"NORMAL":1}"#));
+        assert!(json.contains(r#""min":[0,0,-10],"max":[10,0,0]"#));
+        assert!(!json.contains("COLOR_0"));
+
+        let bin_len = u32_at(&glb, 20 + json_len) as usize;
+        assert_eq!(&glb[24 + json_len..28 + json_len], b"BIN\0");
+        let bin = &glb[28 + json_len..];
+        assert_eq!(bin.len(), bin_len);
+        assert_eq!(bin_len, 5 * 12 * 2 + 4 * 3 * 4);
+
+        // a flat mesh points up
+        let normals = f32s(&bin[60..120]);
+        assert_eq!(normals, [0.0, 1.0, 0.0].repeat(5));
+
+        let mut glb = vec![];
+        Glb::new(&mesh)
+            .z(|v| mesh.vertex(v).position().x)
+            .colors(|_| [255, 0, 0, 255])
+            .write(&mut glb)
+            .unwrap();
+        let json_len = u32_at(&glb, 12) as usize;
+        let json = std::str::from_utf8(&glb[20..20 + json_len]).unwrap();
+        assert!(json.contains(r#""COLOR_0":3"#));
+        assert!(json.contains(r#""min":[0,0,-10],"max":[10,10,0]"#));
+
+        let bin = &glb[28 + json_len..];
+        assert_eq!(bin.len(), 5 * 12 * 2 + 4 * 3 * 4 + 5 * 4);
+        assert_eq!(&bin[bin.len() - 4..], &[255, 0, 0, 255]);
+
+        // the plane z = x tilts the normals towards -x
+        let s = 0.5_f32.sqrt();
+        for n in f32s(&bin[60..120]).chunks(3) {
+            assert!((n[0] + s).abs() < 1e-6 && (n[1] - s).abs() < 1e-6 && n[2].abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_write_glb_non_finite_z() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let v = mesh.insert(Vec2::new(5.0, 5.0));
+
+        for &z in &[f64::NAN, f64::INFINITY, 1e300] {
+            let mut glb = vec![];
+            let err = Glb::new(&mesh)
+                .z(|vid| if vid == v { z } else { 0.0 })
+                .write(&mut glb)
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(glb.is_empty());
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Readers and writers of `DelaunayMesh`es in common file formats.
+
+use std::collections::HashMap;
+
+use crate::geo::Vec3;
+use crate::mesh::{DelaunayMesh, VertexId};
+
+pub mod geojson;
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod svg;
+pub mod triangle;
+pub mod vtk;
+pub mod wkt;
+
+/// A named field defined at each vertex or at each triangle of the mesh, depending on `Id`.
+///
+/// Formats without native vector attributes write the components of `Vector` fields as separate
+/// values, see the documentation of each writer.
+pub enum Field<'a, Id> {
+    Scalar(&'a str, Box<dyn Fn(Id) -> f64 + 'a>),
+    Vector(&'a str, Box<dyn Fn(Id) -> Vec3 + 'a>),
+}
+
+impl<'a, Id> Field<'a, Id> {
+    pub fn scalar(name: &'a str, value: impl Fn(Id) -> f64 + 'a) -> Self {
+        Field::Scalar(name, Box::new(value))
+    }
+
+    pub fn vector(name: &'a str, value: impl Fn(Id) -> Vec3 + 'a) -> Self {
+        Field::Vector(name, Box::new(value))
+    }
+
+    pub fn name(&self) -> &'a str {
+        match self {
+            Field::Scalar(name, _) | Field::Vector(name, _) => name,
+        }
+    }
+}
+
+/// The vertices of the mesh along with a map from their ids to their position in the list, that is
+/// compact zero-based indices that don't have the holes left in the arena by removed vertices.
+pub fn compact_vertices(mesh: &DelaunayMesh) -> (Vec<VertexId>, HashMap<VertexId, usize>) {
+    let vertices = mesh.vertices().map(|(vid, _)| vid).collect::<Vec<_>>();
+    let indices = vertices.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+
+    (vertices, indices)
+}
+
+// the name with its whitespace replaced by underscores, for the formats whose headers are made of
+// whitespace separated words like legacy VTK and PLY.
+pub(crate) fn single_word(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+pub(crate) fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,105 @@
+use std::io::{self, Write};
+
+use crate::export::compact_vertices;
+use crate::mesh::{DelaunayMesh, VertexId};
+
+/// Write the mesh as a Wavefront OBJ file with one `v` line per vertex and one `f` line per
+/// triangle in counter-clockwise order.
+///
+/// The z coordinate of each vertex is given by `z`, see `write_obj_flat` for a flat mesh. Note
+/// that OBJ indices are one-based and they refer to the vertices in the order they're written, not
+/// to the `VertexId`s of the mesh.
+pub fn write_obj(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    z: impl Fn(VertexId) -> f64,
+) -> io::Result<()> {
+    let (vertices, indices) = compact_vertices(mesh);
+
+    for vid in &vertices {
+        let p = mesh.vertex(*vid).position();
+        writeln!(out, "v {} {} {}", p.x, p.y, z(*vid))?;
+    }
+
+    for (_, t) in mesh.triangles() {
+        let [a, b, c] = t.vertices();
+        writeln!(
+            out,
+            "f {} {} {}",
+            indices[&a] + 1,
+            indices[&b] + 1,
+            indices[&c] + 1
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Write the mesh as a Wavefront OBJ file where all the vertices have a zero z coordinate, see
+/// `write_obj`.
+pub fn write_obj_flat(out: &mut impl Write, mesh: &DelaunayMesh) -> io::Result<()> {
+    write_obj(out, mesh, |_| 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::geo::{Bbox, Vec2};
+
+    #[test]
+    fn test_write_obj() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (5.0, 4.0),
+        ] {
+            mesh.insert(Vec2::new(x, y));
+        }
+
+        let mut out = vec![];
+        write_obj(&mut out, &mesh, |v| mesh.vertex(v).position().x * 2.0).unwrap();
+        let obj = String::from_utf8(out).unwrap();
+
+        let vertices = obj
+            .lines()
+            .filter_map(|l| l.strip_prefix("v "))
+            .map(|l| {
+                l.split(' ')
+                    .map(|c| c.parse::<f64>().unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vertices.len(), 5);
+        assert!(vertices.contains(&vec![5.0, 4.0, 10.0]));
+
+        let faces = obj
+            .lines()
+            .filter_map(|l| l.strip_prefix("f "))
+            .collect::<Vec<_>>();
+        assert_eq!(faces.len(), mesh.triangles().count());
+        for f in faces {
+            let f = f
+                .split(' ')
+                .map(|i| i.parse::<usize>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(f.len(), 3);
+            assert!(f.iter().all(|i| (1..=5).contains(i)));
+
+            let [a, b, c] =
+                [0, 1, 2].map(|i| Vec2::new(vertices[f[i] - 1][0], vertices[f[i] - 1][1]));
+            assert!(crate::geo::orient2d(a, b, c) > 0.0);
+        }
+
+        let mut out = vec![];
+        write_obj_flat(&mut out, &mesh).unwrap();
+        let obj = String::from_utf8(out).unwrap();
+        assert!(obj.contains("v 5 4 0\n"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::io::{self, Write};
+
+use crate::export::{compact_vertices, single_word, Field};
+use crate::mesh::{DelaunayMesh, VertexId};
+
+/// The encoding of the body of a PLY file, the header is always ASCII.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// Write the mesh as a PLY file with a `vertex` element with the `x`, `y` and `z` coordinates
+/// followed by the given properties and a `face` element with the vertex indices of each
+/// triangle in counter-clockwise order. All the properties are written as doubles and vector
+/// fields are split in three properties with the `_x`, `_y` and `_z` suffixes. The whitespace in
+/// the names of the properties is replaced by underscores.
+///
+/// The z coordinate of each vertex is given by `z`, see `write_ply_flat` for a flat mesh. The
+/// indices are zero-based and they refer to the vertices in the order they're written, not to the
+/// `VertexId`s of the mesh.
+pub fn write_ply(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    format: PlyFormat,
+    z: impl Fn(VertexId) -> f64,
+    properties: &[Field<VertexId>],
+) -> io::Result<()> {
+    let (vertices, indices) = compact_vertices(mesh);
+    let ntriangles = mesh.triangles().count();
+
+    writeln!(out, "ply")?;
+    match format {
+        PlyFormat::Ascii => writeln!(out, "format ascii 1.0")?,
+        PlyFormat::BinaryLittleEndian => writeln!(out, "format binary_little_endian 1.0")?,
+    }
+    writeln!(out, "element vertex {}", vertices.len())?;
+    for name in &["x", "y", "z"] {
+        writeln!(out, "property double {}", name)?;
+    }
+    for property in properties {
+        match property {
+            Field::Scalar(name, _) => writeln!(out, "property double {}", single_word(name))?,
+            Field::Vector(name, _) => {
+                for c in &["x", "y", "z"] {
+                    writeln!(out, "property double {}_{}", single_word(name), c)?;
+                }
+            }
+        }
+    }
+    writeln!(out, "element face {}", ntriangles)?;
+    writeln!(out, "property list uchar int vertex_indices")?;
+    writeln!(out, "end_header")?;
+
+    for vid in &vertices {
+        let p = mesh.vertex(*vid).position();
+        let mut values = vec![p.x, p.y, z(*vid)];
+        for property in properties {
+            match property {
+                Field::Scalar(_, f) => values.push(f(*vid)),
+                Field::Vector(_, f) => {
+                    let v = f(*vid);
+                    values.extend_from_slice(&[v.x, v.y, v.z]);
+                }
+            }
+        }
+
+        match format {
+            PlyFormat::Ascii => {
+                let line = values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                writeln!(out, "{}", line.join(" "))?;
+            }
+            PlyFormat::BinaryLittleEndian => {
+                for v in values {
+                    out.write_all(&v.to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    for (_, t) in mesh.triangles() {
+        let [a, b, c] = t.vertices();
+        let face = [indices[&a], indices[&b], indices[&c]];
+
+        match format {
+            PlyFormat::Ascii => writeln!(out, "3 {} {} {}", face[0], face[1], face[2])?,
+            PlyFormat::BinaryLittleEndian => {
+                out.write_all(&[3])?;
+                for i in &face {
+                    out.write_all(&(*i as i32).to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the mesh as a PLY file where all the vertices have a zero z coordinate, see
+/// `write_ply`.
+pub fn write_ply_flat(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    format: PlyFormat,
+    properties: &[Field<VertexId>],
+) -> io::Result<()> {
+    write_ply(out, mesh, format, |_| 0.0, properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+
+    use crate::geo::{Bbox, Vec2, Vec3};
+
+    #[test]
+    fn test_write_ply() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (5.0, 4.0),
+        ] {
+            mesh.insert(Vec2::new(x, y));
+        }
+        let ntriangles = mesh.triangles().count();
+
+        let z = |v| mesh.vertex(v).position().x;
+        let properties = [Field::scalar("quality", |v| {
+            mesh.vertex(v).position().y * 10.0
+        })];
+
+        let mut out = vec![];
+        write_ply(&mut out, &mesh, PlyFormat::Ascii, z, &properties).unwrap();
+        let ascii = String::from_utf8(out).unwrap();
+
+        let (header, body) = ascii.split_at(ascii.find("end_header\n").unwrap() + 11);
+        assert!(header.contains("element vertex 5\n"));
+        assert!(header.contains("property double quality\n"));
+        assert!(header.contains(&format!("element face {}\n", ntriangles)));
+
+        let lines = body.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5 + ntriangles);
+        assert!(lines[..5].contains(&"5 4 5 40"));
+        for face in &lines[5..] {
+            let face = face
+                .split(' ')
+                .map(|i| i.parse::<usize>().unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(face[0], 3);
+            assert!(face[1..].iter().all(|i| *i < 5));
+        }
+
+        let mut out = vec![];
+        write_ply(
+            &mut out,
+            &mesh,
+            PlyFormat::BinaryLittleEndian,
+            z,
+            &properties,
+        )
+        .unwrap();
+
+        let header = header.replace("ascii", "binary_little_endian");
+        assert!(out.starts_with(header.as_bytes()));
+
+        let body = &out[header.len()..];
+        assert_eq!(body.len(), 5 * 4 * 8 + ntriangles * (1 + 3 * 4));
+
+        let values = body[..5 * 4 * 8]
+            .chunks(8)
+            .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert!(values.chunks(4).any(|v| v == [5.0, 4.0, 5.0, 40.0]));
+
+        let mut out = vec![];
+        write_ply_flat(
+            &mut out,
+            &mesh,
+            PlyFormat::Ascii,
+            &[
+                Field::vector("normal", |_| Vec3::new(0.0, 0.0, 1.0)),
+                Field::scalar("mean\tcurvature\nelement face 0", |_| 0.0),
+            ],
+        )
+        .unwrap();
+        let ascii = String::from_utf8(out).unwrap();
+        assert!(ascii.contains("property double normal_x\nproperty double normal_y\n"));
+        assert!(ascii.contains("property double mean_curvature_element_face_0\n"));
+        assert_eq!(ascii.matches("\nelement ").count(), 2);
+        assert!(ascii.contains("\n5 4 0 0 0 1 0\n"));
+    }
+}
@@ -0,0 +1,311 @@
+use std::io::{self, Write};
+
+use crate::export::xml_escape;
+use crate::geo::Vec2;
+use crate::mesh::{DelaunayMesh, TriangleId};
+
+/// The style of a triangle in the SVG, colors are any valid SVG color and `None` means that the
+/// fill or the stroke is not drawn at all. Like all the other colors they're escaped when written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Style {
+    pub fill: Option<String>,
+    pub stroke: Option<String>,
+    pub stroke_width: f64,
+}
+
+/// Render a `DelaunayMesh` as an SVG image.
+///
+/// By default only the outline of the triangles is drawn in black over a white background, the
+/// other elements are opt-in:
+///
+/// ```
+/// # use delaunay_mesh::geo::{Bbox, Vec2};
+/// # use delaunay_mesh::DelaunayMesh;
+/// use delaunay_mesh::export::svg::{Style, Svg};
+///
+/// let mut bbox = Bbox::new(Vec2::zero());
+/// bbox.expand(Vec2::new(10.0, 10.0));
+/// let mut mesh = DelaunayMesh::new(bbox);
+/// mesh.insert(Vec2::new(5.0, 5.0));
+///
+/// let mut out = vec![];
+/// Svg::new(&mesh)
+///     .triangle_style(|_| Style {
+///         fill: Some("lightblue".to_string()),
+///         ..Style::default()
+///     })
+///     .vertices(0.5, "red")
+///     .voronoi("gray")
+///     .margin(1.0)
+///     .flip_y(true)
+///     .write(&mut out)
+///     .unwrap();
+/// ```
+pub struct Svg<'a> {
+    mesh: &'a DelaunayMesh,
+    triangle_style: Box<dyn Fn(TriangleId) -> Style + 'a>,
+    background: Option<String>,
+    vertices: Option<(f64, String)>,
+    circumcircles: Option<String>,
+    voronoi: Option<String>,
+    margin: f64,
+    flip_y: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            fill: None,
+            stroke: Some("black".to_string()),
+            stroke_width: 1.0,
+        }
+    }
+}
+
+impl<'a> Svg<'a> {
+    pub fn new(mesh: &'a DelaunayMesh) -> Self {
+        Svg {
+            mesh,
+            triangle_style: Box::new(|_| Style::default()),
+            background: Some("white".to_string()),
+            vertices: None,
+            circumcircles: None,
+            voronoi: None,
+            margin: 0.0,
+            flip_y: false,
+        }
+    }
+
+    /// Style each triangle with the style returned by the given callback.
+    pub fn triangle_style(mut self, style: impl Fn(TriangleId) -> Style + 'a) -> Self {
+        self.triangle_style = Box::new(style);
+        self
+    }
+
+    /// Fill the whole image with the given color, `None` for a transparent background.
+    pub fn background(mut self, color: Option<&str>) -> Self {
+        self.background = color.map(str::to_string);
+        self
+    }
+
+    /// Draw each vertex as a dot with the given radius and color.
+    pub fn vertices(mut self, radius: f64, color: &str) -> Self {
+        self.vertices = Some((radius, color.to_string()));
+        self
+    }
+
+    /// Draw the circumcircle of each triangle with the given stroke color.
+    pub fn circumcircles(mut self, color: &str) -> Self {
+        self.circumcircles = Some(color.to_string());
+        self
+    }
+
+    /// Draw the Voronoi cell (or power cell) of each vertex clipped to the bbox of the mesh with
+    /// the given stroke color.
+    pub fn voronoi(mut self, color: &str) -> Self {
+        self.voronoi = Some(color.to_string());
+        self
+    }
+
+    /// Add the given amount of space around the bbox of the mesh.
+    pub fn margin(mut self, margin: f64) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Whether to flip the y axis so that it points up like in the usual cartesian plane instead
+    /// of down as in SVG.
+    pub fn flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let mesh_bbox = self.mesh.bbox();
+        let mut bbox = mesh_bbox;
+        bbox.enlarge(self.margin);
+        let d = bbox.dimensions();
+
+        writeln!(
+            out,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+<svg xmlns="http://www.w3.org/2000/svg" version="1.1" viewBox="{x} {y} {w} {h}">"#,
+            x = bbox.min().x,
+            y = bbox.min().y,
+            w = d.x,
+            h = d.y,
+        )?;
+
+        if let Some(background) = &self.background {
+            writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" stroke="none" fill="{}" />"#,
+                bbox.min().x,
+                bbox.min().y,
+                d.x,
+                d.y,
+                xml_escape(background)
+            )?;
+        }
+
+        for (tid, _) in self.mesh.triangles() {
+            let style = (self.triangle_style)(tid);
+            let [a, b, c] = self.mesh.triangle_vertices(tid).map(|p| self.point(p));
+
+            writeln!(
+                out,
+                r#"<polygon points="{},{} {},{} {},{}" fill="{}" stroke="{}" stroke-width="{}" />"#,
+                a.x,
+                a.y,
+                b.x,
+                b.y,
+                c.x,
+                c.y,
+                xml_escape(style.fill.as_deref().unwrap_or("none")),
+                xml_escape(style.stroke.as_deref().unwrap_or("none")),
+                style.stroke_width,
+            )?;
+        }
+
+        if let Some(color) = &self.circumcircles {
+            let color = xml_escape(color);
+            for (_, t) in self.mesh.triangles() {
+                let circle = t.circumcircle();
+                let center = self.point(circle.center);
+
+                writeln!(
+                    out,
+                    r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" />"#,
+                    center.x, center.y, circle.radius, color
+                )?;
+            }
+        }
+
+        if let Some(color) = &self.voronoi {
+            let color = xml_escape(color);
+            for (_, cell) in self.mesh.power_diagram() {
+                let cell = mesh_bbox.clip_polygon(&cell);
+                if cell.len() < 3 {
+                    continue;
+                }
+
+                write!(out, r#"<polygon points=""#)?;
+                for (i, p) in cell.into_iter().enumerate() {
+                    let p = self.point(p);
+                    let sep = if i == 0 { "" } else { " " };
+                    write!(out, "{}{},{}", sep, p.x, p.y)?;
+                }
+                writeln!(out, r#"" fill="none" stroke="{}" />"#, color)?;
+            }
+        }
+
+        if let Some((radius, color)) = &self.vertices {
+            let color = xml_escape(color);
+            for (_, v) in self.mesh.vertices() {
+                let p = self.point(v.position());
+
+                writeln!(
+                    out,
+                    r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="none" />"#,
+                    p.x, p.y, radius, color
+                )?;
+            }
+        }
+
+        writeln!(out, "</svg>")
+    }
+
+    // map a point of the mesh to the SVG coordinate system, the bbox of the mesh is mapped to
+    // itself either way.
+    fn point(&self, p: Vec2) -> Vec2 {
+        if self.flip_y {
+            let bbox = self.mesh.bbox();
+            Vec2::new(p.x, bbox.min().y + bbox.max().y - p.y)
+        } else {
+            p
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::geo::Bbox;
+
+    fn render(svg: Svg) -> String {
+        let mut out = vec![];
+        svg.write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_svg() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 20.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 20.0),
+            (0.0, 20.0),
+            (4.0, 15.0),
+        ] {
+            mesh.insert(Vec2::new(x, y));
+        }
+        let ntriangles = mesh.triangles().count();
+
+        let svg = render(Svg::new(&mesh));
+        assert!(svg.contains(r#"viewBox="0 0 10 20""#));
+        assert_eq!(svg.matches("<polygon").count(), ntriangles);
+        assert_eq!(svg.matches("<circle").count(), 0);
+        assert!(svg.contains(r#"fill="none" stroke="black""#));
+        assert!(svg.contains("4,15"));
+
+        let svg = render(
+            Svg::new(&mesh)
+                .triangle_style(|_| Style {
+                    fill: Some("red".to_string()),
+                    stroke: None,
+                    stroke_width: 2.0,
+                })
+                .background(None)
+                .vertices(0.5, "blue")
+                .circumcircles("green")
+                .voronoi("gray")
+                .margin(2.0)
+                .flip_y(true),
+        );
+        assert!(svg.contains(r#"viewBox="-2 -2 14 24""#));
+        assert!(!svg.contains("<rect"));
+        assert!(svg.contains(r#"fill="red" stroke="none" stroke-width="2""#));
+        assert!(svg.contains("4,5"));
+        assert!(!svg.contains("4,15"));
+        assert_eq!(svg.matches(r#"fill="blue""#).count(), 5);
+        assert_eq!(svg.matches(r#"stroke="green""#).count(), ntriangles);
+        assert_eq!(svg.matches(r#"stroke="gray""#).count(), 5);
+
+        let svg = render(
+            Svg::new(&mesh)
+                .triangle_style(|_| Style {
+                    fill: Some(r#"red" onclick="x"#.to_string()),
+                    ..Style::default()
+                })
+                .background(Some("<white>"))
+                .vertices(0.5, "a&b")
+                .circumcircles("\"")
+                .voronoi("<"),
+        );
+        assert!(!svg.contains("onclick=\"x"));
+        assert_eq!(
+            svg.matches(r#"fill="red&quot; onclick=&quot;x""#).count(),
+            ntriangles
+        );
+        assert!(svg.contains(r#"fill="&lt;white&gt;""#));
+        assert_eq!(svg.matches(r#"fill="a&amp;b""#).count(), 5);
+        assert_eq!(svg.matches(r#"stroke="&quot;""#).count(), ntriangles);
+        assert_eq!(svg.matches(r#"stroke="&lt;""#).count(), 5);
+    }
+}
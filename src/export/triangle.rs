@@ -0,0 +1,496 @@
+//! The file formats of Shewchuk's [Triangle](https://www.cs.cmu.edu/~quake/triangle.html)
+//! program.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use crate::export::compact_vertices;
+use crate::geo::{Bbox, Vec2};
+use crate::mesh::{DelaunayMesh, TriangleId, VertexId};
+
+/// The content of a `.node` or `.poly` file. All the indices are zero-based regardless of the
+/// numbering used in the file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Poly {
+    pub points: Vec<Vec2>,
+
+    /// The attributes of each point, all the points have the same number of attributes.
+    pub attributes: Vec<Vec<f64>>,
+
+    /// The boundary marker of each point, empty if the file has no markers.
+    pub markers: Vec<i64>,
+
+    /// The segments between the points, only present in `.poly` files.
+    pub segments: Vec<(usize, usize)>,
+
+    /// A point inside each hole, only present in `.poly` files.
+    pub holes: Vec<Vec2>,
+}
+
+impl Poly {
+    /// Build a mesh with all the points, returning it along with the id of each point.
+    ///
+    /// `DelaunayMesh` doesn't support constraints, therefore the result is the Delaunay
+    /// triangulation of the points and an error of kind `InvalidData` is returned if any of the
+    /// segments is not one of its edges. Points that are not finite are rejected too. The
+    /// triangles that Triangle's `-p` switch would keep are given by `domain_triangles`.
+    pub fn to_mesh(&self) -> io::Result<(DelaunayMesh, Vec<VertexId>)> {
+        if self
+            .points
+            .iter()
+            .any(|p| !p.x.is_finite() || !p.y.is_finite())
+        {
+            return Err(invalid("non finite point".to_string()));
+        }
+
+        let mut bbox = Bbox::new(self.points.first().cloned().unwrap_or_else(Vec2::zero));
+        for p in &self.points {
+            bbox.expand(*p);
+        }
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let vertices = self
+            .points
+            .iter()
+            .map(|p| mesh.insert(*p))
+            .collect::<Vec<_>>();
+
+        let edges = mesh.edges().collect::<HashSet<_>>();
+        for &(a, b) in &self.segments {
+            let (va, vb) = match (vertices.get(a), vertices.get(b)) {
+                (Some(va), Some(vb)) => (*va, *vb),
+                _ => return Err(invalid(format!("segment {}-{} without points", a, b))),
+            };
+
+            if va != vb && !edges.contains(&(va, vb)) && !edges.contains(&(vb, va)) {
+                return Err(invalid(format!(
+                    "segment {}-{} is not an edge of the Delaunay triangulation",
+                    a, b
+                )));
+            }
+        }
+
+        Ok((mesh, vertices))
+    }
+
+    /// The triangles of a mesh built by `to_mesh` that are inside the domain bounded by the
+    /// segments like the output of Triangle with the `-p` switch, that is without the triangles
+    /// inside the holes and the ones outside of the segments that bound the domain.
+    ///
+    /// Such triangles are found by spreading from the boundary of the mesh and from the holes
+    /// without crossing the segments.
+    pub fn domain_triangles(&self, mesh: &DelaunayMesh, vertices: &[VertexId]) -> Vec<TriangleId> {
+        let segments = self
+            .segments
+            .iter()
+            .map(|&(a, b)| (vertices[a], vertices[b]))
+            .flat_map(|(va, vb)| vec![(va, vb), (vb, va)])
+            .collect::<HashSet<_>>();
+
+        let edges = mesh
+            .triangles()
+            .flat_map(|(tid, t)| {
+                let [a, b, c] = t.vertices();
+                vec![((a, b), tid), ((b, c), tid), ((c, a), tid)]
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut eaten = HashSet::new();
+        let mut stack = self
+            .holes
+            .iter()
+            .filter_map(|p| mesh.triangle_at(*p))
+            .collect::<Vec<_>>();
+        for (&(a, b), &tid) in &edges {
+            if !edges.contains_key(&(b, a)) && !segments.contains(&(a, b)) {
+                stack.push(tid);
+            }
+        }
+
+        while let Some(tid) = stack.pop() {
+            if !eaten.insert(tid) {
+                continue;
+            }
+
+            let [a, b, c] = mesh.triangle(tid).vertices();
+            for &(v0, v1) in &[(a, b), (b, c), (c, a)] {
+                if segments.contains(&(v0, v1)) {
+                    continue;
+                }
+                if let Some(neighbor) = edges.get(&(v1, v0)) {
+                    stack.push(*neighbor);
+                }
+            }
+        }
+
+        mesh.triangles()
+            .map(|(tid, _)| tid)
+            .filter(|tid| !eaten.contains(tid))
+            .collect()
+    }
+}
+
+/// Read a `.node` file.
+pub fn read_node(input: impl BufRead) -> io::Result<Poly> {
+    let mut records = Records::new(input)?;
+    let mut poly = Poly::default();
+    read_points(&mut records, &mut poly)?;
+    Ok(poly)
+}
+
+/// Read a `.poly` file.
+///
+/// If the file declares zero points then they're stored in a separate `.node` file and the
+/// segments refer to the numbering of that file, which is assumed to start at 1 like in
+/// Triangle. In that case read the points with `read_node` and assign them to `points`.
+pub fn read_poly(input: impl BufRead) -> io::Result<Poly> {
+    let mut records = Records::new(input)?;
+    let mut poly = Poly::default();
+    let first = read_points(&mut records, &mut poly)?.unwrap_or(1);
+
+    let header = records.next("segments header")?;
+    let nsegments = header.get::<usize>(0)?;
+    for _ in 0..nsegments {
+        let r = records.next("segment")?;
+        let index = |i| {
+            r.get::<usize>(i)?
+                .checked_sub(first)
+                .filter(|v| poly.points.is_empty() || *v < poly.points.len())
+                .ok_or_else(|| invalid(format!("line {}: invalid segment endpoint", r.line)))
+        };
+        poly.segments.push((index(1)?, index(2)?));
+    }
+
+    let nholes = records.next("holes header")?.get::<usize>(0)?;
+    for _ in 0..nholes {
+        let r = records.next("hole")?;
+        poly.holes.push(r.get_point(1)?);
+    }
+
+    // regional attributes and area constraints are ignored
+    Ok(poly)
+}
+
+/// Write the vertices of the mesh as a `.node` file, numbering them from `first` which should
+/// be 0 or 1 like Triangle's `-z` switch.
+pub fn write_node(out: &mut impl Write, mesh: &DelaunayMesh, first: usize) -> io::Result<()> {
+    let (vertices, _) = compact_vertices(mesh);
+
+    writeln!(out, "{} 2 0 0", vertices.len())?;
+    for (i, vid) in vertices.iter().enumerate() {
+        let p = mesh.vertex(*vid).position();
+        writeln!(out, "{} {} {}", i + first, p.x, p.y)?;
+    }
+
+    Ok(())
+}
+
+/// Write the triangles of the mesh as a `.ele` file with the vertices in counter-clockwise order.
+/// The vertices are numbered like in `write_node` and the triangles are numbered from `first`
+/// in the order of `DelaunayMesh::triangles`.
+pub fn write_ele(out: &mut impl Write, mesh: &DelaunayMesh, first: usize) -> io::Result<()> {
+    let (_, indices) = compact_vertices(mesh);
+
+    writeln!(out, "{} 3 0", mesh.triangles().count())?;
+    for (i, (_, t)) in mesh.triangles().enumerate() {
+        let [a, b, c] = t.vertices();
+        writeln!(
+            out,
+            "{} {} {} {}",
+            i + first,
+            indices[&a] + first,
+            indices[&b] + first,
+            indices[&c] + first
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Write the neighbors of the triangles of the mesh as a `.neigh` file, the i-th neighbor of a
+/// triangle is the one opposite to its i-th vertex in the `.ele` file or -1 if there's none.
+pub fn write_neigh(out: &mut impl Write, mesh: &DelaunayMesh, first: usize) -> io::Result<()> {
+    let edges = mesh
+        .triangles()
+        .enumerate()
+        .flat_map(|(i, (_, t))| {
+            let [a, b, c] = t.vertices();
+            vec![((a, b), i), ((b, c), i), ((c, a), i)]
+        })
+        .collect::<HashMap<_, _>>();
+
+    writeln!(out, "{} 3", mesh.triangles().count())?;
+    for (i, (_, t)) in mesh.triangles().enumerate() {
+        let [a, b, c] = t.vertices();
+        write!(out, "{}", i + first)?;
+
+        for &(v0, v1) in &[(b, c), (c, a), (a, b)] {
+            match edges.get(&(v1, v0)) {
+                Some(n) => write!(out, " {}", n + first)?,
+                None => write!(out, " -1")?,
+            }
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+// read the points section shared by .node and .poly files and return the number of the first
+// point, if any.
+fn read_points(records: &mut Records, poly: &mut Poly) -> io::Result<Option<usize>> {
+    let header = records.next("points header")?;
+    let npoints = header.get::<usize>(0)?;
+    let dimension = header.get_or(1, 2)?;
+    let nattributes = header.get_or(2, 0)?;
+    let has_markers = header.get_or::<usize>(3, 0)? > 0;
+
+    if dimension != 2 {
+        return Err(invalid(format!(
+            "line {}: unsupported dimension {}",
+            header.line, dimension
+        )));
+    }
+
+    let mut first = None;
+    for _ in 0..npoints {
+        let r = records.next("point")?;
+
+        let i = r.get::<usize>(0)?;
+        if first.is_none() {
+            first = Some(i);
+        }
+        if first.map(|f| f + poly.points.len()) != Some(i) {
+            return Err(invalid(format!(
+                "line {}: points must be numbered consecutively",
+                r.line
+            )));
+        }
+
+        poly.points.push(r.get_point(1)?);
+        poly.attributes.push(
+            (0..nattributes)
+                .map(|a| r.get(3 + a))
+                .collect::<io::Result<_>>()?,
+        );
+        if has_markers {
+            poly.markers.push(r.get_or(3 + nattributes, 0)?);
+        }
+    }
+
+    Ok(first)
+}
+
+// the non empty lines of a file without comments, split in whitespace separated fields.
+struct Records {
+    records: std::vec::IntoIter<Record>,
+}
+
+struct Record {
+    line: usize,
+    fields: Vec<String>,
+}
+
+impl Records {
+    fn new(input: impl BufRead) -> io::Result<Self> {
+        let mut records = vec![];
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let content = line.split('#').next().unwrap_or("");
+
+            let fields = content
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            if !fields.is_empty() {
+                records.push(Record {
+                    line: i + 1,
+                    fields,
+                });
+            }
+        }
+
+        Ok(Records {
+            records: records.into_iter(),
+        })
+    }
+
+    fn next(&mut self, what: &str) -> io::Result<Record> {
+        self.records
+            .next()
+            .ok_or_else(|| invalid(format!("unexpected end of file, expected {}", what)))
+    }
+}
+
+impl Record {
+    fn get<T: FromStr>(&self, i: usize) -> io::Result<T> {
+        let field = self
+            .fields
+            .get(i)
+            .ok_or_else(|| invalid(format!("line {}: missing field {}", self.line, i + 1)))?;
+
+        field
+            .parse()
+            .map_err(|_| invalid(format!("line {}: invalid field {:?}", self.line, field)))
+    }
+
+    // the point made of the i-th and of the next field, which must be finite numbers.
+    fn get_point(&self, i: usize) -> io::Result<Vec2> {
+        let p: Vec2 = Vec2::new(self.get(i)?, self.get(i + 1)?);
+        if !p.x.is_finite() || !p.y.is_finite() {
+            return Err(invalid(format!("line {}: non finite point", self.line)));
+        }
+
+        Ok(p)
+    }
+
+    fn get_or<T: FromStr>(&self, i: usize, default: T) -> io::Result<T> {
+        if i < self.fields.len() {
+            self.get(i)
+        } else {
+            Ok(default)
+        }
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::geo::{orient2d, polygon_area};
+
+    const SQUARE_POLY: &str = "
+# a square with a square hole
+8 2 1 1
+1 0 0 0.5 1
+2 10 0 0.5 1
+3 10 10 0.5 1
+4 0 10 0.5 1
+5 4 4 0 2
+6 6 4 0 2
+7 6 6 0 2
+8 4 6   # no attribute nor marker
+8 1
+1 1 2 1
+2 2 3 1
+3 3 4 1
+4 4 1 1
+5 5 6 2
+6 6 7 2
+7 7 8 2
+8 8 5 2
+1
+1 5 5
+";
+
+    fn render(f: impl Fn(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut out = vec![];
+        f(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_read_poly() {
+        let err = read_poly(SQUARE_POLY.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 11: missing field 4"));
+
+        let poly = read_poly(SQUARE_POLY.replace("4 6   #", "4 6 1 2 #").as_bytes()).unwrap();
+        assert_eq!(poly.points.len(), 8);
+        assert_eq!(poly.points[4], Vec2::new(4.0, 4.0));
+        assert_eq!(poly.attributes[0], vec![0.5]);
+        assert_eq!(poly.attributes[7], vec![1.0]);
+        assert_eq!(poly.markers, vec![1, 1, 1, 1, 2, 2, 2, 2]);
+        assert_eq!(poly.segments[3], (3, 0));
+        assert_eq!(poly.segments[7], (7, 4));
+        assert_eq!(poly.holes, vec![Vec2::new(5.0, 5.0)]);
+
+        let (mesh, vertices) = poly.to_mesh().unwrap();
+        assert_eq!(vertices.len(), 8);
+        assert_eq!(mesh.vertex(vertices[6]).position(), Vec2::new(6.0, 6.0));
+
+        // the two triangles of the hole are removed
+        let domain = poly.domain_triangles(&mesh, &vertices);
+        assert_eq!(mesh.triangles().count(), 10);
+        assert_eq!(domain.len(), 8);
+        let area = domain
+            .iter()
+            .map(|t| polygon_area(&mesh.triangle_vertices(*t)))
+            .sum::<f64>();
+        assert_eq!(area, 96.0);
+
+        let err = read_node("1 2\n1 nan 2\n".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "line 2: non finite point");
+
+        let err = read_poly(SQUARE_POLY.replace("1 5 5", "1 5 inf").as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // the Delaunay triangulation connects the points at 5,1 and 5,-1 instead
+        let crossing = "4 2 0 0\n1 0 0\n2 10 0\n3 5 1\n4 5 -1\n1 0\n1 1 2\n0\n";
+        let err = read_poly(crossing.as_bytes())
+            .unwrap()
+            .to_mesh()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "segment 0-1 is not an edge of the Delaunay triangulation"
+        );
+
+        let node = read_node("3 2\n0 1.5 2\n1 3 4\n2 5 -1\n".as_bytes()).unwrap();
+        assert_eq!(node.points[2], Vec2::new(5.0, -1.0));
+        assert!(node.segments.is_empty() && node.markers.is_empty());
+        assert_eq!(node.attributes, vec![Vec::<f64>::new(); 3]);
+    }
+
+    #[test]
+    fn test_write_node_ele_neigh() {
+        let points = "5 2 0 0\n1 0 0\n2 10 0\n3 10 10\n4 0 10\n5 4 5\n";
+        let (mesh, _) = read_node(points.as_bytes()).unwrap().to_mesh().unwrap();
+
+        let node = render(|out| write_node(out, &mesh, 1));
+        let reread = read_node(node.as_bytes()).unwrap();
+        assert_eq!(reread.points.len(), 5);
+        assert_eq!(node, points);
+
+        let ele = render(|out| write_ele(out, &mesh, 0));
+        let neigh = render(|out| write_neigh(out, &mesh, 0));
+
+        let parse = |s: &str| {
+            s.lines()
+                .skip(1)
+                .map(|l| {
+                    l.split(' ')
+                        .map(|f| f.parse::<i64>().unwrap())
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let (ele, neigh) = (parse(&ele), parse(&neigh));
+        assert_eq!(ele.len(), 4);
+        assert_eq!(neigh.len(), 4);
+
+        for (t, n) in ele.iter().zip(&neigh) {
+            let p = |i: i64| reread.points[i as usize];
+            assert!(orient2d(p(t[1]), p(t[2]), p(t[3])) > 0.0);
+
+            // each triangle has the center vertex and two neighbors that share it
+            assert!(t[1..].contains(&4));
+            for i in 0..3 {
+                if t[1 + i] == 4 {
+                    assert_eq!(n[1 + i], -1);
+                } else {
+                    let other = &ele[n[1 + i] as usize];
+                    assert!(other[1..].contains(&4));
+                    assert!(!other[1..].contains(&t[1 + i]));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! VTK unstructured grids, both in the legacy format and in the XML one (`.vtu`), where each
+//! triangle is a `VTK_TRIANGLE` cell.
+
+use std::io::{self, Write};
+
+use crate::export::{compact_vertices, single_word, xml_escape, Field};
+use crate::mesh::{DelaunayMesh, TriangleId, VertexId};
+
+// VTK_TRIANGLE
+const TRIANGLE_CELL_TYPE: u8 = 5;
+
+/// Write the mesh as a legacy ASCII VTK file along with the given point and cell data.
+///
+/// The z coordinate of each vertex is given by `z`, see `write_vtk_flat` for a flat mesh. Since
+/// the legacy format doesn't allow whitespace in the names of the fields it's replaced with
+/// underscores.
+pub fn write_vtk(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    z: impl Fn(VertexId) -> f64,
+    point_data: &[Field<VertexId>],
+    cell_data: &[Field<TriangleId>],
+) -> io::Result<()> {
+    let (vertices, indices) = compact_vertices(mesh);
+    let triangles = mesh.triangles().map(|(tid, _)| tid).collect::<Vec<_>>();
+
+    writeln!(out, "# vtk DataFile Version 3.0")?;
+    writeln!(out, "delaunay-mesh")?;
+    writeln!(out, "ASCII")?;
+    writeln!(out, "DATASET UNSTRUCTURED_GRID")?;
+
+    writeln!(out, "POINTS {} double", vertices.len())?;
+    for vid in &vertices {
+        let p = mesh.vertex(*vid).position();
+        writeln!(out, "{} {} {}", p.x, p.y, z(*vid))?;
+    }
+
+    writeln!(out, "CELLS {} {}", triangles.len(), triangles.len() * 4)?;
+    for tid in &triangles {
+        let [a, b, c] = mesh.triangle(*tid).vertices();
+        writeln!(out, "3 {} {} {}", indices[&a], indices[&b], indices[&c])?;
+    }
+
+    writeln!(out, "CELL_TYPES {}", triangles.len())?;
+    for _ in &triangles {
+        writeln!(out, "{}", TRIANGLE_CELL_TYPE)?;
+    }
+
+    if !point_data.is_empty() {
+        writeln!(out, "POINT_DATA {}", vertices.len())?;
+        write_legacy_fields(out, &vertices, point_data)?;
+    }
+    if !cell_data.is_empty() {
+        writeln!(out, "CELL_DATA {}", triangles.len())?;
+        write_legacy_fields(out, &triangles, cell_data)?;
+    }
+
+    Ok(())
+}
+
+/// Write the mesh as an XML VTK unstructured grid (`.vtu`) with ASCII data arrays along with the
+/// given point and cell data.
+///
+/// The z coordinate of each vertex is given by `z`, see `write_vtu_flat` for a flat mesh.
+pub fn write_vtu(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    z: impl Fn(VertexId) -> f64,
+    point_data: &[Field<VertexId>],
+    cell_data: &[Field<TriangleId>],
+) -> io::Result<()> {
+    let (vertices, indices) = compact_vertices(mesh);
+    let triangles = mesh.triangles().map(|(tid, _)| tid).collect::<Vec<_>>();
+
+    writeln!(out, r#"<?xml version="1.0"?>"#)?;
+    writeln!(
+        out,
+        r#"<VTKFile type="UnstructuredGrid" version="0.1" byte_order="LittleEndian">"#
+    )?;
+    writeln!(out, "<UnstructuredGrid>")?;
+    writeln!(
+        out,
+        r#"<Piece NumberOfPoints="{}" NumberOfCells="{}">"#,
+        vertices.len(),
+        triangles.len()
+    )?;
+
+    writeln!(out, "<PointData>")?;
+    write_xml_fields(out, &vertices, point_data)?;
+    writeln!(out, "</PointData>")?;
+
+    writeln!(out, "<CellData>")?;
+    write_xml_fields(out, &triangles, cell_data)?;
+    writeln!(out, "</CellData>")?;
+
+    writeln!(out, "<Points>")?;
+    writeln!(
+        out,
+        r#"<DataArray type="Float64" NumberOfComponents="3" format="ascii">"#
+    )?;
+    for vid in &vertices {
+        let p = mesh.vertex(*vid).position();
+        writeln!(out, "{} {} {}", p.x, p.y, z(*vid))?;
+    }
+    writeln!(out, "</DataArray>")?;
+    writeln!(out, "</Points>")?;
+
+    writeln!(out, "<Cells>")?;
+    writeln!(
+        out,
+        r#"<DataArray type="Int64" Name="connectivity" format="ascii">"#
+    )?;
+    for tid in &triangles {
+        let [a, b, c] = mesh.triangle(*tid).vertices();
+        writeln!(out, "{} {} {}", indices[&a], indices[&b], indices[&c])?;
+    }
+    writeln!(out, "</DataArray>")?;
+
+    writeln!(
+        out,
+        r#"<DataArray type="Int64" Name="offsets" format="ascii">"#
+    )?;
+    for i in 0..triangles.len() {
+        writeln!(out, "{}", (i + 1) * 3)?;
+    }
+    writeln!(out, "</DataArray>")?;
+
+    writeln!(
+        out,
+        r#"<DataArray type="UInt8" Name="types" format="ascii">"#
+    )?;
+    for _ in &triangles {
+        writeln!(out, "{}", TRIANGLE_CELL_TYPE)?;
+    }
+    writeln!(out, "</DataArray>")?;
+    writeln!(out, "</Cells>")?;
+
+    writeln!(out, "</Piece>")?;
+    writeln!(out, "</UnstructuredGrid>")?;
+    writeln!(out, "</VTKFile>")
+}
+
+/// Write the mesh as a legacy ASCII VTK file where all the vertices have a zero z coordinate,
+/// see `write_vtk`.
+pub fn write_vtk_flat(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    point_data: &[Field<VertexId>],
+    cell_data: &[Field<TriangleId>],
+) -> io::Result<()> {
+    write_vtk(out, mesh, |_| 0.0, point_data, cell_data)
+}
+
+/// Write the mesh as an XML VTK unstructured grid where all the vertices have a zero z
+/// coordinate, see `write_vtu`.
+pub fn write_vtu_flat(
+    out: &mut impl Write,
+    mesh: &DelaunayMesh,
+    point_data: &[Field<VertexId>],
+    cell_data: &[Field<TriangleId>],
+) -> io::Result<()> {
+    write_vtu(out, mesh, |_| 0.0, point_data, cell_data)
+}
+
+fn write_legacy_fields<Id: Copy>(
+    out: &mut impl Write,
+    ids: &[Id],
+    fields: &[Field<Id>],
+) -> io::Result<()> {
+    for field in fields {
+        match field {
+            Field::Scalar(name, f) => {
+                writeln!(out, "SCALARS {} double 1", single_word(name))?;
+                writeln!(out, "LOOKUP_TABLE default")?;
+                for id in ids {
+                    writeln!(out, "{}", f(*id))?;
+                }
+            }
+            Field::Vector(name, f) => {
+                writeln!(out, "VECTORS {} double", single_word(name))?;
+                for id in ids {
+                    let v = f(*id);
+                    writeln!(out, "{} {} {}", v.x, v.y, v.z)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_xml_fields<Id: Copy>(
+    out: &mut impl Write,
+    ids: &[Id],
+    fields: &[Field<Id>],
+) -> io::Result<()> {
+    for field in fields {
+        let components = match field {
+            Field::Scalar(..) => 1,
+            Field::Vector(..) => 3,
+        };
+        writeln!(
+            out,
+            r#"<DataArray type="Float64" Name="{}" NumberOfComponents="{}" format="ascii">"#,
+            xml_escape(field.name()),
+            components
+        )?;
+
+        for id in ids {
+            match field {
+                Field::Scalar(_, f) => writeln!(out, "{}", f(*id))?,
+                Field::Vector(_, f) => {
+                    let v = f(*id);
+                    writeln!(out, "{} {} {}", v.x, v.y, v.z)?;
+                }
+            }
+        }
+
+        writeln!(out, "</DataArray>")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::geo::{Bbox, Vec2, Vec3};
+
+    fn mesh() -> DelaunayMesh {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (4.0, 5.0),
+        ] {
+            mesh.insert(Vec2::new(x, y));
+        }
+        mesh
+    }
+
+    #[test]
+    fn test_write_vtk() {
+        let mesh = mesh();
+        let x = |v| mesh.vertex(v).position().x;
+
+        let mut out = vec![];
+        write_vtk(
+            &mut out,
+            &mesh,
+            x,
+            &[
+                Field::scalar("height above sea", x),
+                Field::vector("velocity", |_| Vec3::new(1.0, 2.0, 3.0)),
+            ],
+            &[Field::scalar("id", |t: TriangleId| t.index() as f64)],
+        )
+        .unwrap();
+        let vtk = String::from_utf8(out).unwrap();
+        let lines = vtk.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "# vtk DataFile Version 3.0");
+        assert_eq!(lines[4], "POINTS 5 double");
+        assert!(lines[5..10].contains(&"4 5 4"));
+        assert_eq!(lines[10], "CELLS 4 16");
+        assert!(lines[11..15]
+            .iter()
+            .all(|l| l.starts_with("3 ") && l.contains('4')));
+        assert_eq!(lines[15], "CELL_TYPES 4");
+        assert_eq!(&lines[16..20], &["5"; 4]);
+        assert_eq!(lines[20], "POINT_DATA 5");
+        assert_eq!(lines[21], "SCALARS height_above_sea double 1");
+        assert_eq!(lines[28], "VECTORS velocity double");
+        assert_eq!(&lines[29..34], &["1 2 3"; 5]);
+        assert_eq!(lines[34], "CELL_DATA 4");
+        assert_eq!(lines.len(), 34 + 3 + 4);
+    }
+
+    #[test]
+    fn test_write_vtu() {
+        let mesh = mesh();
+
+        let mut out = vec![];
+        write_vtu_flat(
+            &mut out,
+            &mesh,
+            &[Field::scalar("a<b", |_| 1.0)],
+            &[Field::vector("normal", |_| Vec3::new(0.0, 0.0, 1.0))],
+        )
+        .unwrap();
+        let vtu = String::from_utf8(out).unwrap();
+
+        assert!(vtu.contains(r#"<Piece NumberOfPoints="5" NumberOfCells="4">"#));
+        assert!(vtu.contains(r#"Name="a&lt;b" NumberOfComponents="1""#));
+        assert!(vtu.contains(r#"Name="normal" NumberOfComponents="3""#));
+        assert_eq!(vtu.matches("0 0 1\n").count(), 4);
+        assert!(vtu.contains("4 5 0\n"));
+        assert!(vtu.contains(
+            "<DataArray type=\"Int64\" Name=\"offsets\" format=\"ascii\">\n3\n6\n9\n12\n</DataArray>"
+        ));
+        assert_eq!(vtu.matches("<DataArray").count(), 6);
+        assert_eq!(vtu.matches("</DataArray>").count(), 6);
+        assert!(vtu.ends_with("</VTKFile>\n"));
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::{self, Write};
+
+use crate::geo::Vec2;
+use crate::mesh::DelaunayMesh;
+
+/// Write the triangles of the mesh as a WKT MULTIPOLYGON with closed counter-clockwise rings.
+///
+/// Polygons and line strings with non finite coordinates are skipped by all the writers.
+pub fn write_triangles(out: &mut impl Write, mesh: &DelaunayMesh) -> io::Result<()> {
+    write_multipolygon(
+        out,
+        mesh.triangles()
+            .map(|(tid, _)| mesh.triangle_vertices(tid).to_vec()),
+    )
+}
+
+/// Write the edges of the mesh as a WKT MULTILINESTRING with one line string per edge.
+pub fn write_edges(out: &mut impl Write, mesh: &DelaunayMesh) -> io::Result<()> {
+    let mut edges = mesh
+        .edges()
+        .map(|(a, b)| (mesh.vertex(a).position(), mesh.vertex(b).position()))
+        .filter(|(a, b)| is_finite(*a) && is_finite(*b))
+        .peekable();
+    if edges.peek().is_none() {
+        return writeln!(out, "MULTILINESTRING EMPTY");
+    }
+
+    write!(out, "MULTILINESTRING (")?;
+    for (i, (a, b)) in edges.enumerate() {
+        if i > 0 {
+            write!(out, ", ")?;
+        }
+
+        write!(out, "({} {}, {} {})", a.x, a.y, b.x, b.y)?;
+    }
+    writeln!(out, ")")
+}
+
+/// Write the Voronoi cells (or power cells) of the vertices of the mesh clipped to its bbox as a
+/// WKT MULTIPOLYGON.
+pub fn write_voronoi_cells(out: &mut impl Write, mesh: &DelaunayMesh) -> io::Result<()> {
+    let bbox = mesh.bbox();
+
+    write_multipolygon(
+        out,
+        mesh.power_diagram()
+            .map(|(_, cell)| bbox.clip_polygon(&cell))
+            .filter(|cell| cell.len() >= 3),
+    )
+}
+
+fn write_multipolygon(
+    out: &mut impl Write,
+    polygons: impl Iterator<Item = Vec<Vec2>>,
+) -> io::Result<()> {
+    let mut polygons = polygons
+        .filter(|ring| ring.iter().all(|p| is_finite(*p)))
+        .peekable();
+    if polygons.peek().is_none() {
+        return writeln!(out, "MULTIPOLYGON EMPTY");
+    }
+
+    write!(out, "MULTIPOLYGON (")?;
+    for (i, ring) in polygons.enumerate() {
+        if i > 0 {
+            write!(out, ", ")?;
+        }
+
+        write!(out, "((")?;
+        for (j, p) in ring.iter().chain(ring.first()).enumerate() {
+            if j > 0 {
+                write!(out, ", ")?;
+            }
+            write!(out, "{} {}", p.x, p.y)?;
+        }
+        write!(out, "))")?;
+    }
+    writeln!(out, ")")
+}
+
+// WKT has no representation for non finite numbers, so the geometries with non finite coordinates
+// are skipped like the GeoJSON writers do.
+fn is_finite(p: Vec2) -> bool {
+    p.x.is_finite() && p.y.is_finite()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::geo::Bbox;
+
+    fn render(f: impl Fn(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut out = vec![];
+        f(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_wkt() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        assert_eq!(
+            render(|out| write_triangles(out, &mesh)),
+            "MULTIPOLYGON EMPTY\n"
+        );
+        assert_eq!(
+            render(|out| write_edges(out, &mesh)),
+            "MULTILINESTRING EMPTY\n"
+        );
+
+        for &(x, y) in &[(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)] {
+            mesh.insert(Vec2::new(x, y));
+        }
+
+        let triangles = render(|out| write_triangles(out, &mesh));
+        assert!(
+            [
+                "MULTIPOLYGON (((0 0, 10 0, 0 10, 0 0)))\n",
+                "MULTIPOLYGON (((10 0, 0 10, 0 0, 10 0)))\n",
+                "MULTIPOLYGON (((0 10, 0 0, 10 0, 0 10)))\n",
+            ]
+            .contains(&triangles.as_str()),
+            "{}",
+            triangles
+        );
+
+        let edges = render(|out| write_edges(out, &mesh));
+        assert!(edges.starts_with("MULTILINESTRING (("));
+        assert_eq!(edges.matches('(').count(), 4);
+        for segment in &["0 0, 10 0", "10 0, 0 10", "0 10, 0 0"] {
+            assert!(edges.contains(segment), "{}", edges);
+        }
+
+        let cells = render(|out| write_voronoi_cells(out, &mesh));
+        assert!(cells.starts_with("MULTIPOLYGON ((("));
+        assert_eq!(cells.matches("((").count(), 3);
+    }
+
+    #[test]
+    fn test_wkt_skips_non_finite_polygons() {
+        let ring = |x: f64| vec![Vec2::new(x, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+
+        assert_eq!(
+            render(|out| write_multipolygon(out, vec![ring(f64::NAN)].into_iter())),
+            "MULTIPOLYGON EMPTY\n"
+        );
+        assert_eq!(
+            render(|out| {
+                write_multipolygon(out, vec![ring(f64::INFINITY), ring(0.0)].into_iter())
+            }),
+            "MULTIPOLYGON (((0 0, 1 0, 0 1, 0 0)))\n"
+        );
+    }
+}
@@ -1,74 +1,189 @@
+use std::fmt::Debug;
 use std::ops::{Add, Div, Mul, Sub};
 
+use num_traits::{Float, NumAssign};
+
+/// The floating point types that can be used for the coordinates of the geometric types and of
+/// `DelaunayMesh`, that is `f32` and `f64`.
+///
+/// The predicates like `orient2d` are always evaluated in `f64`, which represents every `f32`
+/// exactly, therefore their sign is exact regardless of the scalar type.
+pub trait Scalar: Float + NumAssign + Default + Debug + 'static {
+    /// Convert the given `f64` to this type, rounding to the nearest value if needed.
+    fn from_f64(v: f64) -> Self;
+
+    /// Convert this value to an `f64`, the conversion must be exact.
+    fn as_f64(self) -> f64;
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Vec2 {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec2<T = f64> {
+    pub x: T,
+    pub y: T,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Vec3 {
     pub x: f64,
     pub y: f64,
+    pub z: f64,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Bbox {
-    min: Vec2,
-    max: Vec2,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bbox<T = f64> {
+    min: Vec2<T>,
+    max: Vec2<T>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub struct Circle {
-    pub center: Vec2,
-    pub radius: f64,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Circle<T = f64> {
+    pub center: Vec2<T>,
+    pub radius: T,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub struct BarycentricCoords {
-    w0: f64,
-    w1: f64,
-    w2: f64,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BarycentricCoords<T = f64> {
+    w0: T,
+    w1: T,
+    w2: T,
+}
+
+/// A polygon with holes. The exterior ring is counter-clockwise while the holes are clockwise,
+/// rings are implicitly closed, that is the first point is not repeated at the end.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Polygon<T = f64> {
+    pub exterior: Vec<Vec2<T>>,
+    pub holes: Vec<Vec<Vec2<T>>>,
+}
+
+impl Scalar for f32 {
+    fn from_f64(v: f64) -> Self {
+        v as f32
+    }
+
+    fn as_f64(self) -> f64 {
+        f64::from(self)
+    }
+}
+
+impl Scalar for f64 {
+    fn from_f64(v: f64) -> Self {
+        v
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
 }
 
-impl Vec2 {
+impl<T: Scalar> Vec2<T> {
     pub fn zero() -> Self {
-        Vec2::new(0.0, 0.0)
+        Vec2::new(T::zero(), T::zero())
     }
 
-    pub fn new(x: f64, y: f64) -> Self {
+    pub fn new(x: T, y: T) -> Self {
         Vec2 { x, y }
     }
 
-    pub fn dist(&self, p: Vec2) -> f64 {
+    /// Convert the coordinates to another scalar type, rounding them if needed.
+    pub fn cast<U: Scalar>(self) -> Vec2<U> {
+        Vec2::new(U::from_f64(self.x.as_f64()), U::from_f64(self.y.as_f64()))
+    }
+
+    pub fn dist(&self, p: Vec2<T>) -> T {
         self.dist2(p).sqrt()
     }
 
-    pub fn dist2(&self, p: Vec2) -> f64 {
+    pub fn dist2(&self, p: Vec2<T>) -> T {
         (*self - p).norm2()
     }
 
+    pub fn norm(&self) -> T {
+        self.norm2().sqrt()
+    }
+
+    pub fn norm2(&self) -> T {
+        self.x.powi(2) + self.y.powi(2)
+    }
+}
+
+impl Vec3 {
+    pub fn zero() -> Self {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    /// The point on the unit sphere at the given latitude and longitude in degrees.
+    pub fn from_lat_lon(lat: f64, lon: f64) -> Self {
+        let (lat, lon) = (lat.to_radians(), lon.to_radians());
+        Vec3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin())
+    }
+
+    /// Latitude and longitude in degrees of the direction of this vector.
+    pub fn lat_lon(&self) -> (f64, f64) {
+        let lat = self.z.atan2(self.x.hypot(self.y));
+        let lon = self.y.atan2(self.x);
+        (lat.to_degrees(), lon.to_degrees())
+    }
+
+    pub fn dot(&self, v: Vec3) -> f64 {
+        self.x * v.x + self.y * v.y + self.z * v.z
+    }
+
+    pub fn cross(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * v.z - self.z * v.y,
+            self.z * v.x - self.x * v.z,
+            self.x * v.y - self.y * v.x,
+        )
+    }
+
     pub fn norm(&self) -> f64 {
         self.norm2().sqrt()
     }
 
     pub fn norm2(&self) -> f64 {
-        self.x.powi(2) + self.y.powi(2)
+        self.dot(*self)
+    }
+
+    pub fn normalized(&self) -> Vec3 {
+        *self / self.norm()
     }
 }
 
-impl Bbox {
-    pub fn new(p: Vec2) -> Self {
+impl<T: Scalar> Bbox<T> {
+    pub fn new(p: Vec2<T>) -> Self {
         Bbox { min: p, max: p }
     }
 
-    pub fn min(&self) -> Vec2 {
+    pub fn min(&self) -> Vec2<T> {
         self.min
     }
 
-    pub fn max(&self) -> Vec2 {
+    pub fn max(&self) -> Vec2<T> {
         self.max
     }
 
-    pub fn center(&self) -> Vec2 {
-        (self.min + self.max) / 2.0
+    /// Convert the corners to another scalar type, rounding them if needed.
+    pub fn cast<U: Scalar>(self) -> Bbox<U> {
+        Bbox {
+            min: self.min.cast(),
+            max: self.max.cast(),
+        }
+    }
+
+    pub fn center(&self) -> Vec2<T> {
+        (self.min + self.max) / T::from_f64(2.0)
     }
 
-    pub fn split(&self, p: Vec2) -> [Bbox; 4] {
+    pub fn split(&self, p: Vec2<T>) -> [Bbox<T>; 4] {
         debug_assert!(self.contains(p));
 
         [
@@ -91,7 +206,7 @@ impl Bbox {
         ]
     }
 
-    pub fn expand(&mut self, p: Vec2) {
+    pub fn expand(&mut self, p: Vec2<T>) {
         self.min.x = self.min.x.min(p.x);
         self.min.y = self.min.y.min(p.y);
 
@@ -99,7 +214,7 @@ impl Bbox {
         self.max.y = self.max.y.max(p.y);
     }
 
-    pub fn enlarge(&mut self, amount: f64) {
+    pub fn enlarge(&mut self, amount: T) {
         self.min.x -= amount;
         self.min.y -= amount;
 
@@ -107,11 +222,11 @@ impl Bbox {
         self.max.y += amount;
     }
 
-    pub fn contains(&self, p: Vec2) -> bool {
+    pub fn contains(&self, p: Vec2<T>) -> bool {
         self.min.x <= p.x && self.min.y <= p.y && self.max.x >= p.x && self.max.y >= p.y
     }
 
-    pub fn intersection(&self, other: Bbox) -> Option<Bbox> {
+    pub fn intersection(&self, other: Bbox<T>) -> Option<Bbox<T>> {
         let min_x = self.min.x.max(other.min.x);
         let min_y = self.min.y.max(other.min.y);
         let max_x = self.max.x.min(other.max.x);
@@ -127,150 +242,602 @@ impl Bbox {
         }
     }
 
-    pub fn dimensions(&self) -> Vec2 {
+    /// Clip the given polygon so that it lies entirely inside this bbox using the
+    /// Sutherland-Hodgman algorithm.
+    pub fn clip_polygon(&self, polygon: &[Vec2<T>]) -> Vec<Vec2<T>> {
+        let polygon = clip_halfplane(polygon, |p| p.x - self.min.x);
+        let polygon = clip_halfplane(&polygon, |p| self.max.x - p.x);
+        let polygon = clip_halfplane(&polygon, |p| p.y - self.min.y);
+        clip_halfplane(&polygon, |p| self.max.y - p.y)
+    }
+
+    pub fn dimensions(&self) -> Vec2<T> {
         self.max - self.min
     }
 
-    pub fn area(&self) -> f64 {
+    pub fn area(&self) -> T {
         let d = self.dimensions();
         d.x * d.y
     }
 }
 
-pub fn collinear(a: Vec2, b: Vec2, c: Vec2) -> bool {
-    let area = a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y);
-    area == 0.0
+pub fn collinear<T: Scalar>(a: Vec2<T>, b: Vec2<T>, c: Vec2<T>) -> bool {
+    orient2d(a, b, c) == 0.0
+}
+
+/// Whether the interiors of the two given counter-clockwise triangles intersect, triangles that
+/// only share an edge or a vertex don't overlap. The test is exact.
+pub fn triangles_overlap<T: Scalar>(t0: [Vec2<T>; 3], t1: [Vec2<T>; 3]) -> bool {
+    // two convex polygons with disjoint interiors are separated by the line through one of their
+    // edges, that is all the vertices of the other polygon lie on the right of such edge or on it
+    let separated = |t: [Vec2<T>; 3], other: [Vec2<T>; 3]| {
+        (0..3).any(|i| {
+            let (a, b) = (t[i], t[(i + 1) % 3]);
+            other.iter().all(|p| orient2d(a, b, *p) <= 0.0)
+        })
+    };
+
+    !separated(t0, t1) && !separated(t1, t0)
+}
+
+/// Clip the given polygon keeping only the part where the signed distance is not negative. The
+/// distance must be an affine function for the result to be correct.
+pub(crate) fn clip_halfplane<T: Scalar>(
+    polygon: &[Vec2<T>],
+    dist: impl Fn(Vec2<T>) -> T,
+) -> Vec<Vec2<T>> {
+    let mut out = Vec::with_capacity(polygon.len() + 1);
+
+    for (i, &cur) in polygon.iter().enumerate() {
+        let prev = polygon[(i + polygon.len() - 1) % polygon.len()];
+
+        let (dprev, dcur) = (dist(prev), dist(cur));
+        if (dprev >= T::zero()) != (dcur >= T::zero()) {
+            out.push(prev + (cur - prev) * (dprev / (dprev - dcur)));
+        }
+        if dcur >= T::zero() {
+            out.push(cur);
+        }
+    }
+
+    out
+}
+
+/// Clip the given polygon so that it lies entirely inside the given convex polygon, whose vertices
+/// must be in counter-clockwise order.
+pub fn clip_convex<T: Scalar>(polygon: &[Vec2<T>], convex: &[Vec2<T>]) -> Vec<Vec2<T>> {
+    let mut polygon = polygon.to_vec();
+
+    for (i, &a) in convex.iter().enumerate() {
+        let b = convex[(i + 1) % convex.len()];
+        let d = b - a;
+
+        polygon = clip_halfplane(&polygon, |p| d.x * (p.y - a.y) - d.y * (p.x - a.x));
+    }
+
+    polygon
+}
+
+/// Signed area of the given polygon, positive if its vertices are counter-clockwise.
+pub fn polygon_area<T: Scalar>(polygon: &[Vec2<T>]) -> T {
+    let mut area = T::zero();
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / T::from_f64(2.0)
+}
+
+/// Whether the given point lies inside the polygon according to the even-odd rule. Points on the
+/// boundary might be considered either inside or outside.
+pub fn polygon_contains<T: Scalar>(polygon: &[Vec2<T>], p: Vec2<T>) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (b.x - a.x) * (p.y - a.y) / (b.y - a.y) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Centroid of the given polygon or `None` if the polygon has zero area.
+pub fn polygon_centroid<T: Scalar>(polygon: &[Vec2<T>]) -> Option<Vec2<T>> {
+    let area = polygon_area(polygon);
+    if area == T::zero() {
+        return None;
+    }
+
+    let mut centroid = Vec2::zero();
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        centroid = centroid + (*a + b) * (a.x * b.y - b.x * a.y);
+    }
+
+    Some(centroid / (T::from_f64(6.0) * area))
+}
+
+/// Orientation of the triangle abc: positive if the vertices are counter-clockwise, negative if
+/// they're clockwise and zero if they're collinear. Counter-clockwise assumes the y axis points
+/// up.
+///
+/// The magnitude is only an approximation of twice the signed area of the triangle, but the sign
+/// is always exact.
+pub fn orient2d<T: Scalar>(a: Vec2<T>, b: Vec2<T>, c: Vec2<T>) -> f64 {
+    // Shewchuk's first error bound, if the approximation is bigger than that then the sign is
+    // surely correct.
+    const ERRBOUND: f64 = (3.0 + 16.0 * f64::EPSILON) * f64::EPSILON;
+
+    let (a, b, c) = (a.cast::<f64>(), b.cast::<f64>(), c.cast::<f64>());
+
+    let detleft = (a.x - c.x) * (b.y - c.y);
+    let detright = (a.y - c.y) * (b.x - c.x);
+    let det = detleft - detright;
+
+    if det.abs() > ERRBOUND * (detleft.abs() + detright.abs()) {
+        return det;
+    }
+
+    orient2d_exact(a, b, c)
+}
+
+/// Whether d lies inside the circle passing through a, b and c which must be in
+/// counter-clockwise order: positive if d is inside, negative if it's outside and zero if the four
+/// points are cocircular.
+///
+/// Like `orient2d` the magnitude is only an approximation, but the sign is always exact.
+pub fn incircle<T: Scalar>(a: Vec2<T>, b: Vec2<T>, c: Vec2<T>, d: Vec2<T>) -> f64 {
+    power_test(
+        (a, T::zero()),
+        (b, T::zero()),
+        (c, T::zero()),
+        (d, T::zero()),
+    )
+}
+
+/// Generalization of `incircle` to weighted points: positive if the power distance between d and
+/// the orthocircle of a, b and c is negative, that is if d conflicts with the triangle abc in the
+/// regular triangulation. The triangle must be counter-clockwise.
+///
+/// Like `orient2d` the magnitude is only an approximation, but the sign is always exact.
+pub fn power_test<T: Scalar>(
+    (a, wa): (Vec2<T>, T),
+    (b, wb): (Vec2<T>, T),
+    (c, wc): (Vec2<T>, T),
+    (d, wd): (Vec2<T>, T),
+) -> f64 {
+    // Shewchuk's first error bound for incircle, see `orient2d`, made a bit more conservative to
+    // account for the weights.
+    const ERRBOUND: f64 = (12.0 + 128.0 * f64::EPSILON) * f64::EPSILON;
+
+    let (a, b, c, d) = (
+        a.cast::<f64>(),
+        b.cast::<f64>(),
+        c.cast::<f64>(),
+        d.cast::<f64>(),
+    );
+    let (wa, wb, wc, wd) = (wa.as_f64(), wb.as_f64(), wc.as_f64(), wd.as_f64());
+
+    let ad = a - d;
+    let bd = b - d;
+    let cd = c - d;
+
+    let alift = ad.norm2() - (wa - wd);
+    let blift = bd.norm2() - (wb - wd);
+    let clift = cd.norm2() - (wc - wd);
+
+    let det = alift * (bd.x * cd.y - cd.x * bd.y)
+        + blift * (cd.x * ad.y - ad.x * cd.y)
+        + clift * (ad.x * bd.y - bd.x * ad.y);
+
+    let alift = ad.norm2() + wa.abs() + wd.abs();
+    let blift = bd.norm2() + wb.abs() + wd.abs();
+    let clift = cd.norm2() + wc.abs() + wd.abs();
+
+    let permanent = alift * ((bd.x * cd.y).abs() + (cd.x * bd.y).abs())
+        + blift * ((cd.x * ad.y).abs() + (ad.x * cd.y).abs())
+        + clift * ((ad.x * bd.y).abs() + (bd.x * ad.y).abs());
+
+    if det.abs() > ERRBOUND * permanent {
+        return det;
+    }
+
+    power_test_exact((a, wa), (b, wb), (c, wc), (d, wd))
+}
+
+/// Orientation of the tetrahedron abcd: positive if d lies below the plane through a, b and c
+/// where above is the side from which a, b and c appear in counter-clockwise order, negative if d
+/// lies above and zero if the four points are coplanar.
+///
+/// Like `orient2d` the magnitude is only an approximation, but the sign is always exact.
+pub fn orient3d(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> f64 {
+    // Shewchuk's first error bound, see `orient2d`
+    const ERRBOUND: f64 = (7.0 + 56.0 * f64::EPSILON) * f64::EPSILON;
+
+    let ad = a - d;
+    let bd = b - d;
+    let cd = c - d;
+
+    let det = ad.x * (bd.y * cd.z - bd.z * cd.y)
+        + bd.x * (cd.y * ad.z - cd.z * ad.y)
+        + cd.x * (ad.y * bd.z - ad.z * bd.y);
+
+    let permanent = ad.x.abs() * ((bd.y * cd.z).abs() + (bd.z * cd.y).abs())
+        + bd.x.abs() * ((cd.y * ad.z).abs() + (cd.z * ad.y).abs())
+        + cd.x.abs() * ((ad.y * bd.z).abs() + (ad.z * bd.y).abs());
+
+    if det.abs() > ERRBOUND * permanent {
+        return det;
+    }
+
+    orient3d_exact(a, b, c, d)
+}
+
+//
+// The exact predicates are computed using floating-point expansions as described in "Adaptive
+// Precision Floating-Point Arithmetic and Fast Robust Geometric Predicates" by Shewchuk. An
+// expansion is a sum of non overlapping floats sorted by increasing magnitude and therefore the
+// sign of the most significant component is the sign of the whole expansion.
+//
+
+fn orient2d_exact(a: Vec2, b: Vec2, c: Vec2) -> f64 {
+    let acx = diff_expansion(a.x, c.x);
+    let acy = diff_expansion(a.y, c.y);
+    let bcx = diff_expansion(b.x, c.x);
+    let bcy = diff_expansion(b.y, c.y);
+
+    let det = expansion_sum(
+        &expansion_product(&acx, &bcy),
+        &negate_expansion(expansion_product(&acy, &bcx)),
+    );
+
+    expansion_estimate(&det)
+}
+
+fn power_test_exact(
+    (a, wa): (Vec2, f64),
+    (b, wb): (Vec2, f64),
+    (c, wc): (Vec2, f64),
+    (d, wd): (Vec2, f64),
+) -> f64 {
+    let adx = diff_expansion(a.x, d.x);
+    let ady = diff_expansion(a.y, d.y);
+    let bdx = diff_expansion(b.x, d.x);
+    let bdy = diff_expansion(b.y, d.y);
+    let cdx = diff_expansion(c.x, d.x);
+    let cdy = diff_expansion(c.y, d.y);
+
+    let lift = |x: &[f64], y: &[f64], w: f64| {
+        let mut l = expansion_sum(&expansion_product(x, x), &expansion_product(y, y));
+        grow_expansion(&mut l, -w);
+        grow_expansion(&mut l, wd);
+        l
+    };
+    let cross = |ax: &[f64], ay: &[f64], bx: &[f64], by: &[f64]| {
+        expansion_sum(
+            &expansion_product(ax, by),
+            &negate_expansion(expansion_product(ay, bx)),
+        )
+    };
+
+    let det = expansion_sum(
+        &expansion_sum(
+            &expansion_product(&lift(&adx, &ady, wa), &cross(&bdx, &bdy, &cdx, &cdy)),
+            &expansion_product(&lift(&bdx, &bdy, wb), &cross(&cdx, &cdy, &adx, &ady)),
+        ),
+        &expansion_product(&lift(&cdx, &cdy, wc), &cross(&adx, &ady, &bdx, &bdy)),
+    );
+
+    expansion_estimate(&det)
+}
+
+fn orient3d_exact(a: Vec3, b: Vec3, c: Vec3, d: Vec3) -> f64 {
+    let diff = |p: Vec3| {
+        [
+            diff_expansion(p.x, d.x),
+            diff_expansion(p.y, d.y),
+            diff_expansion(p.z, d.z),
+        ]
+    };
+    let [adx, ady, adz] = diff(a);
+    let [bdx, bdy, bdz] = diff(b);
+    let [cdx, cdy, cdz] = diff(c);
+
+    let cross = |ax: &[f64], ay: &[f64], bx: &[f64], by: &[f64]| {
+        expansion_sum(
+            &expansion_product(ax, by),
+            &negate_expansion(expansion_product(ay, bx)),
+        )
+    };
+
+    let det = expansion_sum(
+        &expansion_sum(
+            &expansion_product(&adx, &cross(&bdy, &bdz, &cdy, &cdz)),
+            &expansion_product(&bdx, &cross(&cdy, &cdz, &ady, &adz)),
+        ),
+        &expansion_product(&cdx, &cross(&ady, &adz, &bdy, &bdz)),
+    );
+
+    expansion_estimate(&det)
+}
+
+fn expansion_estimate(e: &[f64]) -> f64 {
+    e.iter().rev().find(|c| **c != 0.0).cloned().unwrap_or(0.0)
+}
+
+fn diff_expansion(a: f64, b: f64) -> Vec<f64> {
+    let (x, y) = two_sum(a, -b);
+    vec![y, x]
+}
+
+fn negate_expansion(mut e: Vec<f64>) -> Vec<f64> {
+    for c in &mut e {
+        *c = -*c;
+    }
+    e
+}
+
+fn expansion_sum(e: &[f64], f: &[f64]) -> Vec<f64> {
+    let mut out = e.to_vec();
+    for &c in f {
+        grow_expansion(&mut out, c);
+    }
+    out
+}
+
+fn expansion_product(e: &[f64], f: &[f64]) -> Vec<f64> {
+    let mut out = vec![];
+    for &b in f {
+        for &c in e {
+            let hi = c * b;
+            let lo = c.mul_add(b, -hi);
+
+            grow_expansion(&mut out, lo);
+            grow_expansion(&mut out, hi);
+        }
+    }
+    out
+}
+
+fn grow_expansion(expansion: &mut Vec<f64>, b: f64) {
+    let mut out = Vec::with_capacity(expansion.len() + 1);
+
+    let mut q = b;
+    for &c in expansion.iter() {
+        let (sum, err) = two_sum(q, c);
+        if err != 0.0 {
+            out.push(err);
+        }
+        q = sum;
+    }
+    if q != 0.0 || out.is_empty() {
+        out.push(q);
+    }
+
+    *expansion = out;
+}
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+    (x, (a - av) + (b - bv))
 }
 
-impl Circle {
-    pub fn new(center: Vec2, radius: f64) -> Self {
-        debug_assert!(radius >= 0.0);
+impl<T: Scalar> Circle<T> {
+    pub fn new(center: Vec2<T>, radius: T) -> Self {
+        debug_assert!(radius >= T::zero());
         Circle { center, radius }
     }
 
-    pub fn circumcircle(a: Vec2, b: Vec2, c: Vec2) -> Self {
+    pub fn circumcircle(a: Vec2<T>, b: Vec2<T>, c: Vec2<T>) -> Self {
         // https://en.wikipedia.org/wiki/Circumscribed_circle#Cartesian_coordinates_2
         let b = b - a;
         let c = c - a;
 
-        let d = 2.0 * (b.x * c.y - b.y * c.x);
+        let d = T::from_f64(2.0) * (b.x * c.y - b.y * c.x);
         let x = (c.y * (b.x.powi(2) + b.y.powi(2)) - b.y * (c.x.powi(2) + c.y.powi(2))) / d;
         let y = (b.x * (c.x.powi(2) + c.y.powi(2)) - c.x * (b.x.powi(2) + b.y.powi(2))) / d;
 
         Circle::new(a + Vec2::new(x, y), Vec2::new(x, y).norm())
     }
 
-    pub fn contains(&self, p: Vec2) -> bool {
-        self.center.dist(p) - self.radius <= 1e-4
+    /// The orthocircle of the given weighted points, that is the circle whose power distance from
+    /// all of them is zero. When all the weights are zero this is the circumcircle.
+    ///
+    /// Note that the squared radius of the orthocircle can be negative, in which case the radius
+    /// is clamped to zero.
+    pub fn orthocircle(
+        (a, wa): (Vec2<T>, T),
+        (b, wb): (Vec2<T>, T),
+        (c, wc): (Vec2<T>, T),
+    ) -> Self {
+        let b = b - a;
+        let c = c - a;
+
+        let bl = b.norm2() - (wb - wa);
+        let cl = c.norm2() - (wc - wa);
+
+        let d = T::from_f64(2.0) * (b.x * c.y - b.y * c.x);
+        let x = (c.y * bl - b.y * cl) / d;
+        let y = (b.x * cl - c.x * bl) / d;
+
+        let r2 = Vec2::new(x, y).norm2() - wa;
+        Circle::new(a + Vec2::new(x, y), r2.max(T::zero()).sqrt())
     }
 
-    pub fn bbox(&self) -> Bbox {
+    pub fn contains(&self, p: Vec2<T>) -> bool {
+        self.center.dist(p) - self.radius <= T::from_f64(1e-4)
+    }
+
+    pub fn bbox(&self) -> Bbox<T> {
         let mut b = Bbox::new(self.center);
         b.enlarge(self.radius);
         b
     }
 }
 
-impl BarycentricCoords {
-    pub fn triangle([a, b, c]: [Vec2; 3], p: Vec2) -> Option<Self> {
+impl<T: Scalar> Polygon<T> {
+    /// The area of the polygon excluding its holes.
+    pub fn area(&self) -> T {
+        self.holes
+            .iter()
+            .fold(polygon_area(&self.exterior), |area, h| {
+                area + polygon_area(h)
+            })
+    }
+
+    /// Whether the given point lies inside the polygon but not inside any of its holes.
+    pub fn contains(&self, p: Vec2<T>) -> bool {
+        polygon_contains(&self.exterior, p) && !self.holes.iter().any(|h| polygon_contains(h, p))
+    }
+}
+
+impl<T: Scalar> BarycentricCoords<T> {
+    /// The barycentric coordinates of p with respect to the given triangle or None if the
+    /// triangle is degenerate. Points outside of the triangle have some negative weights.
+    pub fn triangle([a, b, c]: [Vec2<T>; 3], p: Vec2<T>) -> Option<Self> {
         let d = (b.y - c.y) * (a.x - c.x) + (c.x - b.x) * (a.y - c.y);
+        if d == T::zero() {
+            return None;
+        }
 
         let w0 = ((b.y - c.y) * (p.x - c.x) + (c.x - b.x) * (p.y - c.y)) / d;
         let w1 = ((c.y - a.y) * (p.x - c.x) + (a.x - c.x) * (p.y - c.y)) / d;
-        let w2 = 1.0 - w0 - w1;
+        let w2 = T::one() - w0 - w1;
 
-        if w0 + w1 + w2 > 1.0 {
-            None
-        } else {
-            Some(BarycentricCoords { w0, w1, w2 })
-        }
+        Some(BarycentricCoords { w0, w1, w2 })
     }
 
-    pub fn to_point(&self, triangle: [Vec2; 3]) -> Vec2 {
+    pub fn to_point(&self, triangle: [Vec2<T>; 3]) -> Vec2<T> {
         triangle[0] * self.w0 + triangle[1] * self.w1 + triangle[2] * self.w2
     }
 
-    pub fn interpolate(&self, vals: [f64; 3]) -> f64 {
+    pub fn interpolate(&self, vals: [T; 3]) -> T {
         vals[0] * self.w0 + vals[1] * self.w1 + vals[2] * self.w2
     }
 }
 
-impl Add for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Add for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn add(mut self, rhs: Vec2) -> Self::Output {
+    fn add(mut self, rhs: Vec2<T>) -> Self::Output {
         self.x += rhs.x;
         self.y += rhs.y;
         self
     }
 }
 
-impl Add<f64> for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Add<T> for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn add(mut self, rhs: f64) -> Self::Output {
+    fn add(mut self, rhs: T) -> Self::Output {
         self.x += rhs;
         self.y += rhs;
         self
     }
 }
 
-impl Sub for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Sub for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn sub(mut self, rhs: Vec2) -> Self::Output {
+    fn sub(mut self, rhs: Vec2<T>) -> Self::Output {
         self.x -= rhs.x;
         self.y -= rhs.y;
         self
     }
 }
 
-impl Sub<f64> for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Sub<T> for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn sub(mut self, rhs: f64) -> Self::Output {
+    fn sub(mut self, rhs: T) -> Self::Output {
         self.x -= rhs;
         self.y -= rhs;
         self
     }
 }
 
-impl Mul for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Mul for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn mul(mut self, rhs: Vec2) -> Self::Output {
+    fn mul(mut self, rhs: Vec2<T>) -> Self::Output {
         self.x *= rhs.x;
         self.y *= rhs.y;
         self
     }
 }
 
-impl Mul<f64> for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Mul<T> for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn mul(mut self, rhs: f64) -> Self::Output {
+    fn mul(mut self, rhs: T) -> Self::Output {
         self.x *= rhs;
         self.y *= rhs;
         self
     }
 }
 
-impl Div for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Div for Vec2<T> {
+    type Output = Vec2<T>;
 
-    fn div(mut self, rhs: Vec2) -> Self::Output {
+    fn div(mut self, rhs: Vec2<T>) -> Self::Output {
         self.x /= rhs.x;
         self.y /= rhs.y;
         self
     }
 }
 
-impl Div<f64> for Vec2 {
-    type Output = Vec2;
+impl<T: Scalar> Div<T> for Vec2<T> {
+    type Output = Vec2<T>;
+
+    fn div(mut self, rhs: T) -> Self::Output {
+        self.x /= rhs;
+        self.y /= rhs;
+        self
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(mut self, rhs: Vec3) -> Self::Output {
+        self.x += rhs.x;
+        self.y += rhs.y;
+        self.z += rhs.z;
+        self
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(mut self, rhs: Vec3) -> Self::Output {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+        self.z -= rhs.z;
+        self
+    }
+}
+
+impl Mul<f64> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(mut self, rhs: f64) -> Self::Output {
+        self.x *= rhs;
+        self.y *= rhs;
+        self.z *= rhs;
+        self
+    }
+}
+
+impl Div<f64> for Vec3 {
+    type Output = Vec3;
 
     fn div(mut self, rhs: f64) -> Self::Output {
         self.x /= rhs;
         self.y /= rhs;
+        self.z /= rhs;
         self
     }
 }
@@ -302,6 +869,288 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_orient2d() {
+        let a = Vec2::zero();
+        let b = Vec2::new(1.0, 0.0);
+        let c = Vec2::new(0.0, 1.0);
+
+        assert!(orient2d(a, b, c) > 0.0);
+        assert!(orient2d(a, c, b) < 0.0);
+        assert_eq!(orient2d(a, b, Vec2::new(2.0, 0.0)), 0.0);
+
+        // the naive determinant gets the sign of these wrong because of cancellation
+        let b = Vec2::new(12.0, 12.0);
+        let c = Vec2::new(24.0, 24.0);
+        assert_eq!(orient2d(Vec2::new(0.5, 0.5), b, c), 0.0);
+        assert!(orient2d(Vec2::new(0.5 + f64::EPSILON, 0.5), b, c) < 0.0);
+        assert!(orient2d(Vec2::new(0.5, 0.5 + f64::EPSILON), b, c) > 0.0);
+
+        // f32 coordinates are exact in f64, so they're just as robust
+        let (b, c) = (b.cast::<f32>(), c.cast::<f32>());
+        assert_eq!(orient2d(Vec2::new(0.5, 0.5), b, c), 0.0);
+        assert!(orient2d(Vec2::new(0.5 + f32::EPSILON, 0.5), b, c) < 0.0);
+        assert!(orient2d(Vec2::new(0.5, 0.5 + f32::EPSILON), b, c) > 0.0);
+    }
+
+    #[test]
+    fn test_incircle() {
+        let a = Vec2::zero();
+        let b = Vec2::new(1.0, 0.0);
+        let c = Vec2::new(1.0, 1.0);
+
+        assert!(incircle(a, b, c, Vec2::new(0.5, 0.5)) > 0.0);
+        assert!(incircle(a, b, c, Vec2::new(2.0, 2.0)) < 0.0);
+        assert_eq!(incircle(a, b, c, Vec2::new(0.0, 1.0)), 0.0);
+
+        assert!(incircle(a, b, c, Vec2::new(-f64::EPSILON, 1.0)) < 0.0);
+        assert!(incircle(a, b, c, Vec2::new(f64::EPSILON, 1.0)) > 0.0);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_incircle_is_exact(
+            a in (any::<u16>(), any::<u16>()),
+            b in (any::<u16>(), any::<u16>()),
+            c in (any::<u16>(), any::<u16>()),
+            d in (any::<u16>(), any::<u16>()),
+        ) {
+            let v = |(x, y): (u16, u16)| Vec2::new(x.into(), y.into());
+            let (a, b, c, d) = (v(a), v(b), v(c), v(d));
+            prop_assume!(orient2d(a, b, c) > 0.0);
+
+            let i = |f: f64| f as i128;
+            let (adx, ady) = (i(a.x) - i(d.x), i(a.y) - i(d.y));
+            let (bdx, bdy) = (i(b.x) - i(d.x), i(b.y) - i(d.y));
+            let (cdx, cdy) = (i(c.x) - i(d.x), i(c.y) - i(d.y));
+            let exact = (adx * adx + ady * ady) * (bdx * cdy - cdx * bdy)
+                + (bdx * bdx + bdy * bdy) * (cdx * ady - adx * cdy)
+                + (cdx * cdx + cdy * cdy) * (adx * bdy - bdx * ady);
+
+            let ic = incircle(a, b, c, d);
+            prop_assert_eq!(ic == 0.0, exact == 0);
+            prop_assert_eq!(ic > 0.0, exact > 0);
+
+            // the exact version must agree with the fast path
+            prop_assert_eq!(
+                power_test_exact((a, 0.0), (b, 0.0), (c, 0.0), (d, 0.0)).signum(),
+                ic.signum()
+            );
+        }
+    }
+
+    #[test]
+    fn test_power_test_and_orthocircle() {
+        let a = (Vec2::zero(), 0.0);
+        let b = (Vec2::new(4.0, 0.0), 0.0);
+        let c = (Vec2::new(0.0, 4.0), 0.0);
+
+        assert_eq!(
+            Circle::orthocircle(a, b, c),
+            Circle::circumcircle(a.0, b.0, c.0)
+        );
+
+        // (2, 2) is the center of the circumcircle whose squared radius is 8
+        let center = Vec2::new(2.0, 2.0);
+        assert!(power_test(a, b, c, (center, 0.0)) > 0.0);
+        assert!(power_test(a, b, c, (center, -7.0)) > 0.0);
+        assert_eq!(power_test(a, b, c, (center, -8.0)), 0.0);
+        assert!(power_test(a, b, c, (center, -9.0)) < 0.0);
+
+        // heavier vertices shrink the orthocircle
+        let heavy = Circle::orthocircle((a.0, 4.0), (b.0, 4.0), (c.0, 4.0));
+        assert_eq!(heavy, Circle::new(center, 2.0));
+        assert_eq!(
+            Circle::orthocircle((a.0, 10.0), (b.0, 10.0), (c.0, 10.0)),
+            Circle::new(center, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_lat_lon() {
+        let north = Vec3::from_lat_lon(90.0, 0.0);
+        assert!((north - Vec3::new(0.0, 0.0, 1.0)).norm() < 1e-12);
+
+        let p = Vec3::from_lat_lon(45.0, -120.0);
+        assert!((p.norm() - 1.0).abs() < 1e-12);
+
+        let (lat, lon) = p.lat_lon();
+        assert!((lat - 45.0).abs() < 1e-9);
+        assert!((lon + 120.0).abs() < 1e-9);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_orient3d_is_exact(
+            a in (any::<i32>(), any::<i32>(), any::<i32>()),
+            b in (any::<i32>(), any::<i32>(), any::<i32>()),
+            c in (any::<i32>(), any::<i32>(), any::<i32>()),
+            d in (any::<i32>(), any::<i32>(), any::<i32>()),
+            coplanar in any::<bool>(),
+        ) {
+            let v = |(x, y, z): (i32, i32, i32)| Vec3::new(x.into(), y.into(), z.into());
+            let (a, b, c, mut d) = (v(a), v(b), v(c), v(d));
+            if coplanar {
+                d = a + (b - a) * 2.0 - (c - a) * 3.0;
+            }
+
+            let i = |f: f64| f as i128;
+            let [adx, ady, adz] = [i(a.x) - i(d.x), i(a.y) - i(d.y), i(a.z) - i(d.z)];
+            let [bdx, bdy, bdz] = [i(b.x) - i(d.x), i(b.y) - i(d.y), i(b.z) - i(d.z)];
+            let [cdx, cdy, cdz] = [i(c.x) - i(d.x), i(c.y) - i(d.y), i(c.z) - i(d.z)];
+            let exact = adx * (bdy * cdz - bdz * cdy)
+                + bdx * (cdy * adz - cdz * ady)
+                + cdx * (ady * bdz - adz * bdy);
+
+            let o = orient3d(a, b, c, d);
+            prop_assert_eq!(o == 0.0, exact == 0);
+            prop_assert_eq!(o > 0.0, exact > 0);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_orient2d_is_exact(a in rand_vec2(), b in rand_vec2(), c in rand_vec2()) {
+            let i = |f: f64| f as i128;
+            let exact = (i(a.x) - i(c.x)) * (i(b.y) - i(c.y)) - (i(a.y) - i(c.y)) * (i(b.x) - i(c.x));
+
+            let o = orient2d(a, b, c);
+            prop_assert_eq!(o == 0.0, exact == 0);
+            prop_assert_eq!(o > 0.0, exact > 0);
+        }
+    }
+
+    #[test]
+    fn test_barycentric_outside_and_degenerate() {
+        let triangle = [Vec2::zero(), Vec2::new(2.0, 0.0), Vec2::new(0.0, 2.0)];
+
+        let p = Vec2::new(3.0, 1.0);
+        let bary = BarycentricCoords::triangle(triangle, p).unwrap();
+        assert_eq!(
+            bary,
+            BarycentricCoords {
+                w0: -1.0,
+                w1: 1.5,
+                w2: 0.5
+            }
+        );
+        assert_eq!(bary.to_point(triangle), p);
+
+        let degenerate = [Vec2::zero(), Vec2::new(1.0, 1.0), Vec2::new(2.0, 2.0)];
+        assert_eq!(BarycentricCoords::triangle(degenerate, p), None);
+    }
+
+    #[test]
+    fn test_triangles_overlap() {
+        let t = [Vec2::zero(), Vec2::new(2.0, 0.0), Vec2::new(0.0, 2.0)];
+        assert!(triangles_overlap(t, t));
+
+        // sharing an edge or a vertex
+        let adjacent = [
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+        assert!(!triangles_overlap(t, adjacent));
+        let touching = [
+            Vec2::new(2.0, 0.0),
+            Vec2::new(3.0, 0.0),
+            Vec2::new(3.0, 1.0),
+        ];
+        assert!(!triangles_overlap(t, touching));
+
+        // partial overlap with no vertex nor centroid strictly inside the other triangle
+        let partial = [
+            Vec2::new(1.0, 0.0),
+            Vec2::new(3.0, 0.0),
+            Vec2::new(1.0, 1.0),
+        ];
+        assert!(triangles_overlap(t, partial));
+        assert!(triangles_overlap(partial, t));
+
+        let strip = [
+            Vec2::new(-1.0, 0.5),
+            Vec2::new(3.0, 0.5),
+            Vec2::new(3.0, 0.6),
+        ];
+        assert!(triangles_overlap(t, strip));
+    }
+
+    #[test]
+    fn test_polygon_area_and_centroid() {
+        let square = [
+            Vec2::zero(),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+        assert_eq!(polygon_area(&square), 4.0);
+        assert_eq!(polygon_centroid(&square), Some(Vec2::new(1.0, 1.0)));
+
+        let mut cw = square;
+        cw.reverse();
+        assert_eq!(polygon_area(&cw), -4.0);
+        assert_eq!(polygon_centroid(&cw), Some(Vec2::new(1.0, 1.0)));
+
+        assert_eq!(polygon_centroid(&[Vec2::zero(), Vec2::new(1.0, 1.0)]), None);
+
+        let mut hole = square.iter().map(|p| *p * 0.5 + 0.5).collect::<Vec<_>>();
+        hole.reverse();
+        let polygon = Polygon {
+            exterior: square.to_vec(),
+            holes: vec![hole],
+        };
+        assert_eq!(polygon.area(), 3.0);
+        assert!(polygon.contains(Vec2::new(0.25, 1.0)));
+        assert!(!polygon.contains(Vec2::new(1.0, 1.0)));
+        assert!(!polygon.contains(Vec2::new(3.0, 1.0)));
+    }
+
+    #[test]
+    fn test_clip_convex() {
+        let square = [
+            Vec2::zero(),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+        let triangle = [
+            Vec2::new(1.0, -1.0),
+            Vec2::new(3.0, 1.0),
+            Vec2::new(1.0, 3.0),
+        ];
+
+        let clipped = clip_convex(&square, &triangle);
+        assert_eq!(polygon_area(&clipped), 2.0);
+        assert_eq!(polygon_centroid(&clipped), Some(Vec2::new(1.5, 1.0)));
+        assert_eq!(clip_convex(&triangle, &triangle).len(), 3);
+    }
+
+    #[test]
+    fn test_bbox_clip_polygon() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(2.0, 2.0));
+
+        let triangle = [
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(1.0, -1.0),
+            Vec2::new(3.0, 3.0),
+        ];
+        let clipped = bbox.clip_polygon(&triangle);
+        assert!(clipped.iter().all(|p| bbox.contains(*p)));
+        assert!((polygon_area(&clipped) - 3.5).abs() < 1e-9);
+
+        let inside = [Vec2::zero(), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)];
+        assert_eq!(bbox.clip_polygon(&inside), inside.to_vec());
+
+        let outside = [
+            Vec2::new(5.0, 5.0),
+            Vec2::new(6.0, 5.0),
+            Vec2::new(5.0, 6.0),
+        ];
+        assert!(bbox.clip_polygon(&outside).is_empty());
+    }
+
     proptest! {
         #[test]
         fn prop_circle_always_contains_known_points(c in rand_vec2(), r in any::<u32>()) {
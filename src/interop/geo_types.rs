@@ -0,0 +1,126 @@
+use geo_types::{Coord, CoordNum, LineString, MultiPolygon, Point, Rect};
+
+use crate::geo::{Bbox, Polygon, Scalar, Vec2};
+use crate::mesh::DelaunayMesh;
+
+impl<T: CoordNum> From<Coord<T>> for Vec2<T> {
+    fn from(c: Coord<T>) -> Self {
+        Vec2 { x: c.x, y: c.y }
+    }
+}
+
+impl<T: CoordNum> From<Vec2<T>> for Coord<T> {
+    fn from(p: Vec2<T>) -> Self {
+        Coord { x: p.x, y: p.y }
+    }
+}
+
+impl<T: CoordNum> From<Point<T>> for Vec2<T> {
+    fn from(p: Point<T>) -> Self {
+        p.0.into()
+    }
+}
+
+impl<T: CoordNum> From<Vec2<T>> for Point<T> {
+    fn from(p: Vec2<T>) -> Self {
+        Point(p.into())
+    }
+}
+
+impl<T: Scalar> From<Rect<T>> for Bbox<T> {
+    fn from(r: Rect<T>) -> Self {
+        let mut bbox = Bbox::new(r.min().into());
+        bbox.expand(r.max().into());
+        bbox
+    }
+}
+
+impl<T: Scalar> From<Bbox<T>> for Rect<T> {
+    fn from(bbox: Bbox<T>) -> Self {
+        Rect::new(bbox.min(), bbox.max())
+    }
+}
+
+impl<T: Scalar> From<Polygon<T>> for geo_types::Polygon<T> {
+    fn from(polygon: Polygon<T>) -> Self {
+        let ring = |r: Vec<Vec2<T>>| r.into_iter().map(Coord::from).collect::<LineString<T>>();
+
+        geo_types::Polygon::new(
+            ring(polygon.exterior),
+            polygon.holes.into_iter().map(ring).collect(),
+        )
+    }
+}
+
+/// The triangles of the mesh, like the ones returned by `DelaunayMesh::triangles`, as
+/// counter-clockwise polygons.
+impl<T: Scalar> From<&DelaunayMesh<T>> for MultiPolygon<T> {
+    fn from(mesh: &DelaunayMesh<T>) -> Self {
+        mesh.triangles()
+            .map(|(tid, _)| {
+                let ring = mesh.triangle_vertices(tid).to_vec();
+                geo_types::Polygon::new(ring.into_iter().map(Coord::from).collect(), vec![])
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_points_and_rects() {
+        let p = Vec2::new(1.5, -2.0);
+        assert_eq!(Coord::from(p), Coord { x: 1.5, y: -2.0 });
+        assert_eq!(Vec2::from(Point::new(1.5, -2.0)), p);
+
+        let rect = Rect::<f32>::new(Coord { x: 3.0, y: 1.0 }, Coord { x: -1.0, y: 2.0 });
+        let bbox = Bbox::from(rect);
+        assert_eq!(bbox.min(), Vec2::new(-1.0, 1.0));
+        assert_eq!(bbox.max(), Vec2::new(3.0, 2.0));
+        assert_eq!(Rect::from(bbox), rect);
+    }
+
+    #[test]
+    fn test_mesh_to_multi_polygon() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (4.0, 6.0),
+        ] {
+            mesh.insert(Vec2::new(x, y));
+        }
+
+        let multi = MultiPolygon::from(&mesh);
+        assert_eq!(multi.0.len(), mesh.triangles().count());
+
+        let area = multi
+            .iter()
+            .map(|t| {
+                let ring = t
+                    .exterior()
+                    .coords()
+                    .map(|c| Vec2::from(*c))
+                    .collect::<Vec<_>>();
+                assert_eq!(ring.len(), 4);
+                crate::geo::polygon_area(&ring[..3])
+            })
+            .sum::<f64>();
+        assert_eq!(area, 100.0);
+
+        let polygon = Polygon {
+            exterior: vec![Vec2::zero(), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)],
+            holes: vec![],
+        };
+        let converted = geo_types::Polygon::from(polygon);
+        assert_eq!(converted.exterior().0.len(), 4);
+        assert!(converted.interiors().is_empty());
+    }
+}
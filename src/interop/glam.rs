@@ -0,0 +1,41 @@
+use crate::geo::Vec2;
+
+impl From<glam::Vec2> for Vec2<f32> {
+    fn from(v: glam::Vec2) -> Self {
+        Vec2::new(v.x, v.y)
+    }
+}
+
+impl From<Vec2<f32>> for glam::Vec2 {
+    fn from(v: Vec2<f32>) -> Self {
+        glam::Vec2::new(v.x, v.y)
+    }
+}
+
+impl From<glam::DVec2> for Vec2<f64> {
+    fn from(v: glam::DVec2) -> Self {
+        Vec2::new(v.x, v.y)
+    }
+}
+
+impl From<Vec2<f64>> for glam::DVec2 {
+    fn from(v: Vec2<f64>) -> Self {
+        glam::DVec2::new(v.x, v.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let v = glam::Vec2::new(1.5, -2.0);
+        assert_eq!(Vec2::from(v), Vec2::new(1.5, -2.0));
+        assert_eq!(glam::Vec2::from(Vec2::from(v)), v);
+
+        let dv = glam::DVec2::new(1.5, -2.0);
+        assert_eq!(Vec2::from(dv), Vec2::new(1.5, -2.0));
+        assert_eq!(glam::DVec2::from(Vec2::from(dv)), dv);
+    }
+}
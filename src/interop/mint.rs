@@ -0,0 +1,47 @@
+use crate::geo::Vec2;
+
+impl<T> From<mint::Point2<T>> for Vec2<T> {
+    fn from(p: mint::Point2<T>) -> Self {
+        Vec2 { x: p.x, y: p.y }
+    }
+}
+
+impl<T> From<Vec2<T>> for mint::Point2<T> {
+    fn from(p: Vec2<T>) -> Self {
+        mint::Point2 { x: p.x, y: p.y }
+    }
+}
+
+impl<T> From<mint::Vector2<T>> for Vec2<T> {
+    fn from(v: mint::Vector2<T>) -> Self {
+        Vec2 { x: v.x, y: v.y }
+    }
+}
+
+impl<T> From<Vec2<T>> for mint::Vector2<T> {
+    fn from(v: Vec2<T>) -> Self {
+        mint::Vector2 { x: v.x, y: v.y }
+    }
+}
+
+impl<T> mint::IntoMint for Vec2<T> {
+    type MintType = mint::Vector2<T>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let p = Vec2::new(1.5, -2.0);
+
+        let mp: mint::Point2<f64> = p.into();
+        assert_eq!((mp.x, mp.y), (1.5, -2.0));
+        assert_eq!(Vec2::from(mp), p);
+
+        let mv: mint::Vector2<f32> = Vec2::new(1.5_f32, -2.0).into();
+        assert_eq!((mv.x, mv.y), (1.5, -2.0));
+        assert_eq!(Vec2::from(mv), Vec2::new(1.5, -2.0));
+    }
+}
@@ -0,0 +1,12 @@
+//! Conversions between the geometric types of this crate and the ones of other crates. Each
+//! conversion is behind the cargo feature with the same name as the crate, that is `mint`,
+//! `glam`, `nalgebra` and `geo-types`.
+
+#[cfg(feature = "geo-types")]
+mod geo_types;
+#[cfg(feature = "glam")]
+mod glam;
+#[cfg(feature = "mint")]
+mod mint;
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
@@ -0,0 +1,43 @@
+use crate::geo::Vec2;
+
+impl<T: nalgebra::Scalar> From<nalgebra::Point2<T>> for Vec2<T> {
+    fn from(p: nalgebra::Point2<T>) -> Self {
+        let [[x, y]] = p.coords.data.0;
+        Vec2 { x, y }
+    }
+}
+
+impl<T: nalgebra::Scalar> From<Vec2<T>> for nalgebra::Point2<T> {
+    fn from(p: Vec2<T>) -> Self {
+        nalgebra::Point2::new(p.x, p.y)
+    }
+}
+
+impl<T: nalgebra::Scalar> From<nalgebra::Vector2<T>> for Vec2<T> {
+    fn from(v: nalgebra::Vector2<T>) -> Self {
+        let [[x, y]] = v.data.0;
+        Vec2 { x, y }
+    }
+}
+
+impl<T: nalgebra::Scalar> From<Vec2<T>> for nalgebra::Vector2<T> {
+    fn from(v: Vec2<T>) -> Self {
+        nalgebra::Vector2::new(v.x, v.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let p = nalgebra::Point2::new(1.5, -2.0);
+        assert_eq!(Vec2::from(p), Vec2::new(1.5, -2.0));
+        assert_eq!(nalgebra::Point2::from(Vec2::from(p)), p);
+
+        let v = nalgebra::Vector2::new(1.5_f32, -2.0);
+        assert_eq!(Vec2::from(v), Vec2::new(1.5, -2.0));
+        assert_eq!(nalgebra::Vector2::from(Vec2::from(v)), v);
+    }
+}
@@ -1,6 +1,16 @@
 pub mod arena;
 pub mod bvh;
+mod cavity;
+pub mod contour;
+pub mod export;
 pub mod geo;
+pub mod interop;
+pub mod lowpoly;
 pub mod mesh;
+pub mod periodic;
+pub mod raster;
+pub mod sampling;
+pub mod sphere;
+pub mod terrain;
 
 pub use mesh::DelaunayMesh;
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use crate::geo::{Bbox, Vec2};
+use crate::mesh::{DelaunayMesh, TriangleId, VertexId};
+use crate::raster::{rasterize_colors, Grid};
+
+/// An in-memory RGB image with 3 bytes per pixel in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+/// A low-poly approximation of an image, that is a triangulation of the image where each triangle
+/// is filled with the mean color of the pixels it covers.
+///
+/// The points are inserted greedily one at a time at the pixel with the highest error in the
+/// current approximation, so that the triangulation refines the areas with the most details
+/// first. The points lie in the image space, that is the pixel at `(x, y)` covers the square
+/// from `(x, y)` to `(x + 1, y + 1)`.
+#[derive(Debug)]
+pub struct LowPoly {
+    mesh: DelaunayMesh,
+    grid: Grid,
+    colors: HashMap<TriangleId, [u8; 3]>,
+}
+
+// the flat color approximation of the image over a triangle.
+#[derive(Debug, Clone, Copy)]
+struct TriangleStats {
+    vertices: [VertexId; 3],
+    color: [u8; 3],
+
+    // the pixel with the highest squared error and its error.
+    worst: Option<(Vec2, u32)>,
+}
+
+impl RgbImage {
+    pub fn new(width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width * height * 3);
+
+        RgbImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * self.width + x) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+}
+
+impl LowPoly {
+    /// Build the low-poly approximation of the given image by inserting at most `npoints` points
+    /// other than the ones needed to cover the image. The image must not be empty.
+    pub fn new(image: &RgbImage, npoints: usize) -> Self {
+        assert!(image.width > 0 && image.height > 0);
+
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(image.width as f64, image.height as f64));
+
+        let mut lowpoly = LowPoly {
+            mesh: DelaunayMesh::new(bbox),
+            grid: Grid::new(image.width, image.height, bbox),
+            colors: HashMap::new(),
+        };
+
+        lowpoly.mesh.insert(bbox.min());
+        lowpoly.mesh.insert(Vec2::new(bbox.max().x, bbox.min().y));
+        lowpoly.mesh.insert(bbox.max());
+        lowpoly.mesh.insert(Vec2::new(bbox.min().x, bbox.max().y));
+        lowpoly.cover_border();
+
+        let mut stats = HashMap::new();
+        for _ in 0..npoints {
+            stats = lowpoly.update_stats(image, stats);
+
+            let worst = stats
+                .values()
+                .filter_map(|s| s.worst)
+                .max_by_key(|(_, err)| *err);
+
+            match worst {
+                Some((p, err)) if err > 0 => {
+                    lowpoly.mesh.insert(p);
+                    lowpoly.cover_border();
+                }
+                _ => break,
+            }
+        }
+
+        stats = lowpoly.update_stats(image, stats);
+        lowpoly.colors = stats.into_iter().map(|(tid, s)| (tid, s.color)).collect();
+
+        lowpoly
+    }
+
+    pub fn mesh(&self) -> &DelaunayMesh {
+        &self.mesh
+    }
+
+    /// The mean color of the pixels covered by the given triangle.
+    pub fn color(&self, tid: TriangleId) -> [u8; 3] {
+        self.colors[&tid]
+    }
+
+    /// Render the approximation in an image with the same size as the original one.
+    pub fn render(&self) -> RgbImage {
+        let rgba = rasterize_colors(
+            &self.mesh,
+            &self.grid,
+            |tid| {
+                let [r, g, b] = self.color(tid);
+                [r, g, b, 255]
+            },
+            [0; 4],
+        );
+
+        let pixels = rgba
+            .chunks(4)
+            .flat_map(|c| c[..3].iter().cloned())
+            .collect();
+        RgbImage::new(self.grid.width, self.grid.height, pixels)
+    }
+
+    // recompute the stats of the triangles that changed since the last time, that is the ones
+    // that are new or whose ids have been reused for different vertices.
+    fn update_stats(
+        &self,
+        image: &RgbImage,
+        mut old: HashMap<TriangleId, TriangleStats>,
+    ) -> HashMap<TriangleId, TriangleStats> {
+        self.mesh
+            .triangles()
+            .map(|(tid, t)| match old.remove(&tid) {
+                Some(s) if s.vertices == t.vertices() => (tid, s),
+                _ => (tid, self.triangle_stats(image, tid)),
+            })
+            .collect()
+    }
+
+    fn triangle_stats(&self, image: &RgbImage, tid: TriangleId) -> TriangleStats {
+        let triangle = self.mesh.triangle_vertices(tid);
+
+        let mut sum = [0_u64; 3];
+        let mut count = 0;
+        self.grid.rasterize_triangle(triangle, |x, y, _| {
+            for (s, c) in sum.iter_mut().zip(image.pixel(x, y).iter()) {
+                *s += u64::from(*c);
+            }
+            count += 1;
+        });
+
+        let color = if count == 0 {
+            // the triangle is too thin to cover any pixel, just sample the image at its centroid
+            let c = (triangle[0] + triangle[1] + triangle[2]) / 3.0;
+            let x = (c.x as usize).min(image.width - 1);
+            let y = (c.y as usize).min(image.height - 1);
+            image.pixel(x, y)
+        } else {
+            let mean = |s: u64| ((s + count / 2) / count) as u8;
+            [mean(sum[0]), mean(sum[1]), mean(sum[2])]
+        };
+
+        // pixels at the vertices are excluded because inserting them would be a no-op
+        let mut worst = None;
+        self.grid.rasterize_triangle(triangle, |x, y, _| {
+            let err = image
+                .pixel(x, y)
+                .iter()
+                .zip(color.iter())
+                .map(|(a, b)| (i32::from(*a) - i32::from(*b)).pow(2) as u32)
+                .sum::<u32>();
+
+            let p = self.grid.pixel_center(x, y);
+            if worst.is_none_or(|(_, e)| err > e) && !triangle.contains(&p) {
+                worst = Some((p, err));
+            }
+        });
+
+        TriangleStats {
+            vertices: self.mesh.triangle(tid).vertices(),
+            color,
+            worst,
+        }
+    }
+
+    // insert points along the border of the image until the sides of the image are edges of the
+    // mesh, otherwise the triangles along the border could be connected to the bounding vertices
+    // of the mesh and the image wouldn't be entirely covered.
+    fn cover_border(&mut self) {
+        let bbox = self.mesh.bbox();
+        let (min, max) = (bbox.min(), bbox.max());
+
+        loop {
+            let mut missing = vec![];
+
+            // each side as whether it's horizontal and its position
+            let sides = [(true, min.y), (true, max.y), (false, min.x), (false, max.x)];
+            for &(horizontal, value) in &sides {
+                let mut border = self
+                    .mesh
+                    .vertices()
+                    .map(|(vid, v)| {
+                        let p = v.position();
+                        if horizontal {
+                            (p.y, p.x, vid)
+                        } else {
+                            (p.x, p.y, vid)
+                        }
+                    })
+                    .filter(|(across, _, _)| *across == value)
+                    .map(|(_, along, vid)| (along, vid))
+                    .collect::<Vec<_>>();
+                border.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+                for w in border.windows(2) {
+                    let (v0, v1) = (w[0].1, w[1].1);
+                    let connected = self
+                        .mesh
+                        .vertex_triangles(v0)
+                        .iter()
+                        .any(|t| self.mesh.triangle(*t).vertices().contains(&v1));
+
+                    if !connected {
+                        let (p0, p1) = (
+                            self.mesh.vertex(v0).position(),
+                            self.mesh.vertex(v1).position(),
+                        );
+                        missing.push((p0 + p1) / 2.0);
+                    }
+                }
+            }
+
+            if missing.is_empty() {
+                break;
+            }
+
+            for p in missing {
+                self.mesh.insert(p);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn image(width: usize, height: usize, f: impl Fn(usize, usize) -> [u8; 3]) -> RgbImage {
+        let mut pixels = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend_from_slice(&f(x, y));
+            }
+        }
+        RgbImage::new(width, height, pixels)
+    }
+
+    #[test]
+    fn test_uniform_image() {
+        let img = image(40, 30, |_, _| [10, 20, 30]);
+
+        let lowpoly = LowPoly::new(&img, 100);
+        assert_eq!(lowpoly.mesh().vertices().count(), 4);
+        assert_eq!(lowpoly.render(), img);
+    }
+
+    #[test]
+    fn test_two_colors() {
+        let img = image(
+            32,
+            32,
+            |x, y| {
+                if x + y < 32 {
+                    [255, 0, 0]
+                } else {
+                    [0, 0, 255]
+                }
+            },
+        );
+
+        let lowpoly = LowPoly::new(&img, 200);
+        let render = lowpoly.render();
+
+        let wrong = (0..32)
+            .flat_map(|y| (0..32).map(move |x| (x, y)))
+            .filter(|&(x, y)| render.pixel(x, y) != img.pixel(x, y))
+            .count();
+        assert!(wrong < 32, "{} wrong pixels", wrong);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn prop_lowpoly_covers_the_image(
+            (width, height) in (1_usize..40, 1_usize..40),
+            seed in any::<u64>(),
+            npoints in 0_usize..60,
+        ) {
+            let img = image(width, height, |x, y| {
+                let h = (x as u64 * 31 + y as u64 * 17) ^ seed;
+                [(h % 256) as u8, (h / 7 % 256) as u8, (h / 13 % 256) as u8]
+            });
+
+            let lowpoly = LowPoly::new(&img, npoints);
+            prop_assert!(lowpoly.mesh().vertices().count() <= 4 + npoints + 4 * (width + height));
+
+            let mut covered = vec![0; width * height];
+            for (tid, _) in lowpoly.mesh().triangles() {
+                lowpoly.grid.rasterize_triangle(lowpoly.mesh().triangle_vertices(tid), |x, y, _| {
+                    covered[y * width + x] += 1;
+                });
+            }
+            prop_assert!(covered.iter().all(|c| *c == 1));
+        }
+    }
+}
@@ -1,46 +1,180 @@
 use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
 
-use crate::arena::{Arena, ArenaId};
+use crate::arena::{Arena, ArenaId, Node};
 use crate::bvh::Bvh;
-use crate::geo::{BarycentricCoords, Bbox, Circle, Vec2};
+use crate::cavity;
+use crate::geo::{
+    orient2d, polygon_centroid, power_test, triangles_overlap, Bbox, Circle, Scalar, Vec2,
+};
 
-pub type TriangleId = ArenaId<Triangle>;
-pub type VertexId = ArenaId<Vertex>;
+pub type TriangleId = ArenaId<TriangleTag>;
+pub type VertexId = ArenaId<VertexTag>;
 
+/// The tag of the `TriangleId`s, it doesn't depend on the scalar type of the mesh so that the ids
+/// are the same for `DelaunayMesh<f32>` and `DelaunayMesh<f64>`.
 #[derive(Debug)]
-pub struct DelaunayMesh {
-    triangles: Arena<Triangle>,
-    vertices: Arena<Vertex>,
-    triangles_index: Bvh<TriangleId>,
+pub enum TriangleTag {}
+
+/// The tag of the `VertexId`s, see `TriangleTag`.
+#[derive(Debug)]
+pub enum VertexTag {}
+
+// padding added to the input bbox to place the bounding vertices.
+const BOUNDING_PADDING: f64 = 20.0;
+
+const SNAPSHOT_MAGIC: &[u8; 4] = b"DMSH";
+const SNAPSHOT_VERSION: u32 = 1;
+
+// marker for a missing index in snapshots.
+const SNAPSHOT_NONE: u64 = u64::MAX;
+
+/// A Delaunay triangulation of the points inserted so far.
+///
+/// The mesh is generic over the `Scalar` type of the coordinates, `f64` by default. With `f32`
+/// the vertices and the triangles take less memory while the predicates stay exact. The ids don't
+/// depend on the scalar type. Everything built on top of the mesh (`contour`, `terrain`,
+/// `raster`, `lowpoly`, `sampling`, `periodic` and `export`) works on `f64` meshes only, use
+/// `cast` to convert an `f32` mesh first.
+///
+/// With the `serde` feature the mesh can be serialized and deserialized, only the triangles and
+/// the vertices are stored while the spatial index and the edges are rebuilt on load. Ids are
+/// preserved. Deserialization performs the same checks as `read_snapshot` and fails if the data
+/// is inconsistent or if the triangles are not a valid triangulation of the bbox.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "MeshData<T>",
+        bound(deserialize = "T: Scalar + serde::Deserialize<'de>")
+    )
+)]
+pub struct DelaunayMesh<T = f64> {
+    triangles: Arena<Triangle<T>, TriangleTag>,
+    vertices: Arena<Vertex<T>, VertexTag>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    triangles_index: Bvh<TriangleId, T>,
+
+    // map from each directed edge to the triangle it belongs to, the twin edge (if any) belongs to
+    // the adjacent triangle.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    edges: HashMap<(VertexId, VertexId), TriangleId>,
+
+    // the vertices that are hidden, they're inserted again when they're not redundant anymore.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hidden_vertices: HashSet<VertexId>,
 
     // bbox of the points that are to be inserted in the mesh. Doesn't take into account the
     // padding for the initial super triangles.
-    input_bbox: Bbox,
+    input_bbox: Bbox<T>,
+}
+
+// the serialized part of a `DelaunayMesh`, everything else is derived from it.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct MeshData<T> {
+    triangles: Arena<Triangle<T>, TriangleTag>,
+    vertices: Arena<Vertex<T>, VertexTag>,
+    input_bbox: Bbox<T>,
+}
+
+/// A broken invariant of a `DelaunayMesh` as found by `DelaunayMesh::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// The vertices of the triangle are not in counter-clockwise order.
+    NotCounterClockwise(TriangleId),
+
+    /// The vertices of the triangle are collinear and therefore the triangle has zero area.
+    Degenerate(TriangleId),
+
+    /// The interiors of the two triangles intersect, or they share the same directed edge.
+    Overlapping(TriangleId, TriangleId),
+
+    /// The opposite vertex of the triangle that shares the given edge lies strictly inside the
+    /// circumcircle of the triangle (or has a negative power distance from its orthocircle).
+    NonDelaunayEdge {
+        triangle: TriangleId,
+        edge: (VertexId, VertexId),
+        opposite: VertexId,
+    },
+
+    /// The triangle refers to a vertex that has been removed.
+    DanglingVertex {
+        triangle: TriangleId,
+        vertex: VertexId,
+    },
+
+    /// The vertex is not part of any triangle.
+    UnusedVertex(VertexId),
+
+    /// The vertex is hidden but it's not redundant, that is it would be part of the regular
+    /// triangulation.
+    NotRedundant(VertexId),
+
+    /// The triangle is not present in the spatial index.
+    NotIndexed(TriangleId),
+
+    /// The spatial index refers to a triangle that has been removed or whose circumcircle
+    /// changed.
+    StaleIndexEntry(TriangleId),
+}
+
+/// All the broken invariants of a `DelaunayMesh`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub violations: Vec<InvariantViolation>,
 }
 
+// the triangles to remove to insert a vertex, the boundary of their union and the vertices that
+// become redundant.
+struct Cavity {
+    triangles: HashSet<TriangleId>,
+    boundary: Vec<(VertexId, VertexId)>,
+    redundant: HashSet<VertexId>,
+}
+
+/// A triangle of the mesh, its vertices are always in counter-clockwise order.
 #[derive(Debug)]
-pub struct Triangle {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Triangle<T = f64> {
     vertices: [VertexId; 3],
-    circumcircle: Circle,
+    circumcircle: Circle<T>,
+
+    // bbox of the triangle and its circumcircle used in the spatial index.
+    bbox: Bbox<T>,
 }
 
 #[derive(Debug)]
-pub struct Vertex {
-    position: Vec2,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vertex<T = f64> {
+    position: Vec2<T>,
+    weight: T,
+
+    // whether the vertex is redundant, that is it's not part of the regular triangulation because
+    // of the weights of the other vertices.
+    hidden: bool,
+
+    // one of the triangles the vertex belongs to, might be stale if the vertex is hidden.
+    triangle: Option<TriangleId>,
 }
 
-impl DelaunayMesh {
-    pub fn new(mut bbox: Bbox) -> Self {
+impl<T: Scalar> DelaunayMesh<T> {
+    pub fn new(mut bbox: Bbox<T>) -> Self {
         let input_bbox = bbox;
 
         // add a bit of padding to account for the super triangles and to avoid degenerate
         // triangles.
-        bbox.enlarge(20.0);
+        bbox.enlarge(T::from_f64(BOUNDING_PADDING));
 
         let mut dm = DelaunayMesh {
             triangles: Arena::new(),
             vertices: Arena::new(),
             triangles_index: Bvh::new(bbox),
+            edges: HashMap::new(),
+            hidden_vertices: HashSet::new(),
             input_bbox,
         };
 
@@ -58,28 +192,49 @@ impl DelaunayMesh {
         dm
     }
 
-    pub fn bbox(&self) -> Bbox {
+    pub fn bbox(&self) -> Bbox<T> {
         self.input_bbox
     }
 
-    pub fn vertices(&self) -> impl Iterator<Item = (VertexId, &Vertex)> {
+    /// Convert the mesh to another scalar type keeping the ids of its vertices and of its
+    /// triangles. The conversion from `f32` to `f64` is exact, while the other way around the
+    /// positions are rounded and the triangulation might not be Delaunay anymore, see `validate`.
+    pub fn cast<U: Scalar>(&self) -> DelaunayMesh<U> {
+        let vertices = self.vertices.map(|v| Vertex {
+            position: v.position.cast(),
+            weight: U::from_f64(v.weight.as_f64()),
+            hidden: v.hidden,
+            triangle: v.triangle,
+        });
+        let triangles = self.triangles.map(|t| t.vertices);
+
+        DelaunayMesh::from_parts(self.input_bbox.cast(), vertices, triangles)
+            .expect("the parts of a mesh are consistent")
+    }
+
+    /// The vertices of the mesh, hidden vertices are not included.
+    pub fn vertices(&self) -> impl Iterator<Item = (VertexId, &Vertex<T>)> {
         self.vertices
             .enumerate()
-            .filter(move |(_, v)| self.input_bbox.contains(v.position))
+            .filter(move |(_, v)| !v.hidden && self.input_bbox.contains(v.position))
     }
 
-    pub fn vertex(&self, vid: VertexId) -> &Vertex {
+    pub fn vertex(&self, vid: VertexId) -> &Vertex<T> {
         &self.vertices[vid]
     }
 
-    pub fn triangles(&self) -> impl Iterator<Item = (TriangleId, &Triangle)> {
+    pub fn triangle(&self, tid: TriangleId) -> &Triangle<T> {
+        &self.triangles[tid]
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = (TriangleId, &Triangle<T>)> {
         // exclude initial super triangles
         self.triangles
             .enumerate()
             .filter(move |(_, t)| !self.is_super_triangle(t))
     }
 
-    pub fn triangle_vertices(&self, id: TriangleId) -> [Vec2; 3] {
+    pub fn triangle_vertices(&self, id: TriangleId) -> [Vec2<T>; 3] {
         let vs = self.triangles[id].vertices;
 
         [
@@ -89,15 +244,123 @@ impl DelaunayMesh {
         ]
     }
 
-    pub fn enclosing_triangle(&self, p: Vec2) -> Option<&TriangleId> {
+    pub fn enclosing_triangle(&self, p: Vec2<T>) -> Option<&TriangleId> {
         self.triangles_index
             .enclosing(p, |t, p| {
-                BarycentricCoords::triangle(self.triangle_vertices(*t), p).is_some()
+                let [a, b, c] = self.triangle_vertices(*t);
+                orient2d(a, b, p) >= 0.0 && orient2d(b, c, p) >= 0.0 && orient2d(c, a, p) >= 0.0
             })
             .next()
     }
 
-    pub fn insert(&mut self, p: Vec2) {
+    /// The triangle of the mesh that contains the given point, if any. Unlike
+    /// `enclosing_triangle` the triangles connected to the bounding vertices are never returned,
+    /// that is this is None for points outside of all the triangles returned by `triangles()`.
+    pub fn triangle_at(&self, p: Vec2<T>) -> Option<TriangleId> {
+        self.triangles_index
+            .enclosing(p, |t, p| {
+                let [a, b, c] = self.triangle_vertices(*t);
+                orient2d(a, b, p) >= 0.0 && orient2d(b, c, p) >= 0.0 && orient2d(c, a, p) >= 0.0
+            })
+            .find(|t| !self.is_super_triangle(&self.triangles[**t]))
+            .cloned()
+    }
+
+    /// Insert the given point in the mesh and return its id. If there's already a vertex at the
+    /// same position then its id is returned and the mesh is left untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the point lies outside of `bbox()`, see `try_insert` for a non panicking
+    /// version.
+    pub fn insert(&mut self, p: Vec2<T>) -> VertexId {
+        self.try_insert(p)
+            .expect("the point must lie inside the bbox of the mesh")
+    }
+
+    /// Like `insert`, but return None and leave the mesh untouched if the point lies outside of
+    /// `bbox()` or it's not a number.
+    pub fn try_insert(&mut self, p: Vec2<T>) -> Option<VertexId> {
+        match self.find_vertex(p) {
+            Some(vid) => Some(vid),
+            None => self.try_insert_weighted(p, T::zero()),
+        }
+    }
+
+    /// Insert the given weighted point in the mesh and return its id. When the vertices have
+    /// weights the mesh is a regular triangulation instead of a Delaunay one, that is the
+    /// circumcircles are replaced by orthocircles and containment is replaced by a negative power
+    /// distance.
+    ///
+    /// Points that are redundant because of the weights of the other points are not part of the
+    /// triangulation, they're hidden and don't appear in `vertices()`. Inserting a point can also
+    /// hide existing vertices. Hidden vertices are restored by `move_vertex` once the vertices
+    /// that made them redundant move away.
+    ///
+    /// Weights should be small compared to the size of `bbox()`, heavy points that would hide the
+    /// initial super triangles are always considered redundant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the point lies outside of `bbox()`, see `try_insert_weighted` for a non
+    /// panicking version.
+    pub fn insert_weighted(&mut self, p: Vec2<T>, weight: T) -> VertexId {
+        self.try_insert_weighted(p, weight)
+            .expect("the point must lie inside the bbox of the mesh")
+    }
+
+    /// Like `insert_weighted`, but return None and leave the mesh untouched if the point lies
+    /// outside of `bbox()` or it's not a number.
+    pub fn try_insert_weighted(&mut self, p: Vec2<T>, weight: T) -> Option<VertexId> {
+        // the super triangles cover a bit more than the bbox, but the vertices and triangles in
+        // such padding are not considered part of the mesh.
+        if !self.input_bbox.contains(p) {
+            return None;
+        }
+
+        let mut vertex = Vertex::new(p);
+        vertex.weight = weight;
+
+        let vp = self.vertices.push(vertex);
+        self.insert_vertex(vp);
+
+        Some(vp)
+    }
+
+    /// Insert the given vertex in the triangulation, or hide it if it's redundant. Return whether
+    /// the vertex was inserted.
+    fn insert_vertex(&mut self, vp: VertexId) -> bool {
+        let start = *self
+            .enclosing_triangle(self.vertices[vp].position)
+            .expect("the super triangles cover the whole bbox");
+
+        let cavity = match self.cavity(vp, start) {
+            Some(cavity) => cavity,
+            None => {
+                self.set_hidden(vp, true);
+                return false;
+            }
+        };
+
+        for vid in cavity.redundant {
+            self.set_hidden(vid, true);
+        }
+
+        for tri in &cavity.triangles {
+            self.remove_triangle(*tri);
+        }
+
+        for (v0, v1) in cavity.boundary {
+            self.insert_triangle(v0, v1, vp);
+        }
+
+        self.set_hidden(vp, false);
+        true
+    }
+
+    /// The cavity of the Bowyer-Watson insertion of the given vertex starting from the triangle
+    /// that encloses it, or None if the vertex is redundant.
+    fn cavity(&self, vp: VertexId, start: TriangleId) -> Option<Cavity> {
         //
         // The idea here is to first find all the triangles whose circumcircle contains the new
         // point.
@@ -129,106 +392,1806 @@ impl DelaunayMesh {
         // inside the circumcircles of both triangles.
         //
 
-        let enclosing_triangles = self
-            .triangles_index
-            .enclosing(p, |&tid, p| self.triangles[tid].circumcircle.contains(p))
-            .cloned()
-            .collect::<HashSet<_>>();
+        // The triangles to remove form a connected region that contains the triangle enclosing
+        // the new point, therefore they can be found by visiting the neighbors of such triangle.
+        // If the enclosing triangle doesn't conflict with the new point, then the point is
+        // redundant.
+        if !self.in_conflict(start, vp) {
+            return None;
+        }
 
-        let boundary = self.triangles_boundary(&enclosing_triangles);
+        let enclosing_triangles = self.conflict_region(start, |tid| self.in_conflict(tid, vp));
 
-        for tri in &enclosing_triangles {
-            self.remove_triangle(*tri);
+        let boundary = cavity::region_boundary(&enclosing_triangles, &self.edges, |tid| {
+            self.triangles[tid].vertices
+        });
+
+        // the vertices inside the region are now redundant
+        let boundary_vertices = boundary
+            .iter()
+            .flat_map(|(v0, v1)| vec![*v0, *v1])
+            .collect::<HashSet<_>>();
+        let redundant = enclosing_triangles
+            .iter()
+            .flat_map(|tid| self.triangles[*tid].vertices.to_vec())
+            .filter(|v| !boundary_vertices.contains(v))
+            .collect::<HashSet<_>>();
+
+        if redundant
+            .iter()
+            .any(|v| !self.input_bbox.contains(self.vertices[*v].position))
+        {
+            return None;
         }
 
-        let vp = self.vertices.push(Vertex::new(p));
-        for (v0, v1) in boundary {
-            self.insert_triangle(v0, v1, vp);
+        Some(Cavity {
+            triangles: enclosing_triangles,
+            boundary,
+            redundant,
+        })
+    }
+
+    fn set_hidden(&mut self, vid: VertexId, hidden: bool) {
+        self.vertices[vid].hidden = hidden;
+        if hidden {
+            self.hidden_vertices.insert(vid);
+        } else {
+            self.hidden_vertices.remove(&vid);
         }
     }
 
-    fn is_super_triangle(&self, t: &Triangle) -> bool {
-        t.vertices
-            .iter()
-            .any(|v| !self.input_bbox.contains(self.vertices[*v].position))
+    // insert again the hidden vertices that are not redundant anymore. Inserting a vertex can
+    // only hide other vertices, so a single pass is enough.
+    fn restore_hidden_vertices(&mut self) {
+        let mut hidden = self.hidden_vertices.iter().copied().collect::<Vec<_>>();
+        hidden.sort();
+
+        for vid in hidden {
+            if self.vertices[vid].hidden {
+                self.insert_vertex(vid);
+            }
+        }
     }
 
-    fn insert_triangle(&mut self, va: VertexId, vb: VertexId, vc: VertexId) -> TriangleId {
+    /// Flip the edge between the two given vertices, that is replace the two triangles sharing
+    /// the edge with the two triangles sharing the other diagonal of the quadrilateral they form.
+    ///
+    /// Return the two new triangles or `None` if there's no such edge, it's on the boundary of
+    /// the mesh or the quadrilateral is not strictly convex.
+    ///
+    /// Note that flipping edges can make the mesh non Delaunay and `insert` requires the mesh to
+    /// be Delaunay, call `legalize` to restore the Delaunay property.
+    pub fn flip_edge(&mut self, va: VertexId, vb: VertexId) -> Option<[TriangleId; 2]> {
+        //
+        //       c                 c
+        //      / \               /|\
+        //     /   \             / | \
+        //    a-----b    ==>    a  |  b
+        //     \   /             \ | /
+        //      \ /               \|/
+        //       d                 d
+        //
+        let t0 = *self.edges.get(&(va, vb))?;
+        let t1 = *self.edges.get(&(vb, va))?;
+
+        let vc = self.opposite_vertex(t0, (va, vb));
+        let vd = self.opposite_vertex(t1, (va, vb));
+
         let a = self.vertices[va].position;
         let b = self.vertices[vb].position;
         let c = self.vertices[vc].position;
+        let d = self.vertices[vd].position;
 
-        let circumcircle = Circle::circumcircle(a, b, c);
-        let tri = self.triangles.push(Triangle {
-            vertices: [va, vb, vc],
-            circumcircle,
+        if orient2d(c, a, d) <= 0.0 || orient2d(d, b, c) <= 0.0 {
+            return None;
+        }
+
+        self.remove_triangle(t0);
+        self.remove_triangle(t1);
+
+        Some([
+            self.insert_triangle(vc, va, vd),
+            self.insert_triangle(vd, vb, vc),
+        ])
+    }
+
+    /// Restore the Delaunay property of the mesh by repeatedly flipping the edges whose opposite
+    /// vertices lie inside the circumcircles of the adjacent triangles (Lawson's algorithm).
+    ///
+    /// Return the number of flipped edges.
+    pub fn legalize(&mut self) -> usize {
+        let edges = self.edges.keys().cloned().collect();
+        self.legalize_edges(edges)
+    }
+
+    fn legalize_edges(&mut self, mut stack: Vec<(VertexId, VertexId)>) -> usize {
+        let mut flips = 0;
+
+        while let Some((va, vb)) = stack.pop() {
+            let (t0, t1) = match (self.edges.get(&(va, vb)), self.edges.get(&(vb, va))) {
+                (Some(t0), Some(t1)) => (*t0, *t1),
+                _ => continue,
+            };
+
+            let vd = self.opposite_vertex(t1, (va, vb));
+            if !self.in_conflict(t0, vd) {
+                continue;
+            }
+
+            let vc = self.opposite_vertex(t0, (va, vb));
+            if self.flip_edge(va, vb).is_some() {
+                flips += 1;
+                stack.extend_from_slice(&[(va, vc), (vc, vb), (vb, vd), (vd, va)]);
+            }
+        }
+
+        flips
+    }
+
+    /// Move the given vertex to the new position keeping its id and repair the triangulation
+    /// locally around it. If the vertex stays inside the polygon formed by its neighbors then the
+    /// triangulation is repaired by flipping edges, otherwise the vertex is removed and inserted
+    /// again in its new position.
+    ///
+    /// Weighted vertices are always removed and inserted again because moving a vertex can hide
+    /// its neighbors or the vertex itself. A hidden vertex can become visible again after being
+    /// moved, and so can the vertices that were hidden by the vertex at its old position, so the
+    /// result is always the regular triangulation of the vertices.
+    ///
+    /// Return whether the vertex was moved, that is not the case if the vertex is not in the mesh,
+    /// the new position lies outside `bbox()` or it's already taken by another vertex.
+    pub fn move_vertex(&mut self, vid: VertexId, p: Vec2<T>) -> bool {
+        let old = match self.vertices.get(vid) {
+            Some(v) => v.position,
+            None => return false,
+        };
+
+        if !self.input_bbox.contains(old) || !self.input_bbox.contains(p) {
+            return false;
+        }
+
+        if old == p {
+            return true;
+        }
+
+        if self.find_vertex(p).is_some() {
+            return false;
+        }
+
+        if self.vertices[vid].hidden {
+            self.vertices[vid].position = p;
+            self.insert_vertex(vid);
+            return true;
+        }
+
+        let star = self.vertex_star(vid);
+
+        let weighted = star.iter().any(|&tid| {
+            self.triangles[tid]
+                .vertices
+                .iter()
+                .any(|v| self.vertices[*v].weight != T::zero())
+        });
+        let inside_star = star.iter().all(|&tid| {
+            let [_, b, c] = self.triangle_vertices_from(tid, vid);
+            orient2d(p, self.vertices[b].position, self.vertices[c].position) > 0.0
         });
 
-        self.triangles_index.insert(tri, circumcircle.bbox());
-        tri
+        if inside_star && !weighted {
+            self.vertices[vid].position = p;
+
+            let mut edges = Vec::with_capacity(star.len() * 2);
+            for tid in star {
+                self.update_circumcircle(tid);
+
+                let [_, b, c] = self.triangle_vertices_from(tid, vid);
+                edges.push((vid, b));
+                edges.push((b, c));
+            }
+
+            self.legalize_edges(edges);
+        } else {
+            self.remove_vertex_triangles(vid, star);
+
+            self.vertices[vid].position = p;
+            self.insert_vertex(vid);
+        }
+
+        // the vertices hidden by the old position might not be redundant anymore
+        if !self.hidden_vertices.is_empty() {
+            self.restore_hidden_vertices();
+        }
+
+        true
     }
 
-    fn remove_triangle(&mut self, tri: TriangleId) {
-        self.triangles_index
-            .remove(&tri, self.triangles[tri].circumcircle.bbox());
+    /// The Voronoi cell of the given vertex, that is the polygon formed by the circumcenters of
+    /// the triangles around the vertex in counter-clockwise order. If the vertices have weights
+    /// then this is the cell of the power diagram. Hidden vertices have no cell.
+    ///
+    /// The cells of the vertices near the boundary of the mesh extend beyond `bbox()`, use
+    /// `Bbox::clip_polygon` to clip them.
+    pub fn voronoi_cell(&self, vid: VertexId) -> Vec<Vec2<T>> {
+        self.vertex_star(vid)
+            .into_iter()
+            .map(|tid| self.triangles[tid].circumcircle.center)
+            .collect()
+    }
 
-        self.triangles.remove(tri);
+    /// The power diagram of the mesh, that is the cells of all the vertices as returned by
+    /// `voronoi_cell`. It's the dual of the regular triangulation and it's the Voronoi diagram
+    /// when all the weights are zero.
+    pub fn power_diagram(&self) -> impl Iterator<Item = (VertexId, Vec<Vec2<T>>)> + '_ {
+        self.vertices()
+            .map(move |(vid, _)| (vid, self.voronoi_cell(vid)))
+    }
+
+    /// Perform the given number of iterations of Lloyd relaxation, that is move each vertex to
+    /// the centroid of its Voronoi cell clipped to `bbox()`. This spreads the points evenly and
+    /// improves the shape of the triangles.
+    pub fn relax(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            let centroids = self
+                .vertices()
+                .filter_map(|(vid, _)| {
+                    let cell = self.input_bbox.clip_polygon(&self.voronoi_cell(vid));
+                    Some((vid, polygon_centroid(&cell)?))
+                })
+                .collect::<Vec<_>>();
+
+            for (vid, centroid) in centroids {
+                self.move_vertex(vid, centroid);
+            }
+        }
+    }
+
+    /// Find the vertex at exactly the given position, if any. Hidden vertices are ignored.
+    pub fn find_vertex(&self, p: Vec2<T>) -> Option<VertexId> {
+        let tid = self.enclosing_triangle(p)?;
+
+        self.triangles[*tid]
+            .vertices
+            .iter()
+            .find(|v| self.vertices[**v].position == p)
+            .cloned()
+    }
+
+    /// Find the vertex closest to the given point, if any. Hidden vertices are ignored.
+    pub fn nearest_vertex(&self, p: Vec2<T>) -> Option<VertexId> {
+        let start = *self.enclosing_triangle(p)?;
+
+        let dist2 = |vid: VertexId| self.vertices[vid].position.dist2(p);
+
+        // All the vertices closer than the nearest vertex found so far lie in the triangles that
+        // intersect the disk centered at p passing through it. Such triangles are connected across
+        // the edges that intersect the disk, therefore visiting them from the enclosing triangle
+        // finds the nearest vertex even when the bounding vertices are closer to p than it.
+        let mut nearest: Option<VertexId> = None;
+        let mut visited = HashSet::new();
+        visited.insert(start);
+
+        let mut stack = vec![start];
+        while let Some(tid) = stack.pop() {
+            let [va, vb, vc] = self.triangles[tid].vertices;
+
+            for &v in &[va, vb, vc] {
+                let closer = nearest.is_none_or(|n| dist2(v) < dist2(n));
+                if closer && self.input_bbox.contains(self.vertices[v].position) {
+                    nearest = Some(v);
+                }
+            }
+
+            for &(v0, v1) in &[(va, vb), (vb, vc), (vc, va)] {
+                let neighbor = match self.edges.get(&(v1, v0)) {
+                    Some(n) => *n,
+                    None => continue,
+                };
+
+                let a = self.vertices[v0].position;
+                let b = self.vertices[v1].position;
+                let reachable = nearest.is_none_or(|n| segment_dist2(a, b, p) <= dist2(n));
+                if reachable && visited.insert(neighbor) {
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        nearest
+    }
+
+    /// The triangles around the given vertex in counter-clockwise order, like `triangles()` the
+    /// triangles connected to the bounding vertices are not returned.
+    pub fn vertex_triangles(&self, vid: VertexId) -> Vec<TriangleId> {
+        self.vertex_star(vid)
+            .into_iter()
+            .filter(|tid| !self.is_super_triangle(&self.triangles[*tid]))
+            .collect()
+    }
+
+    /// The edges of the mesh, each one is returned once oriented like in one of the triangles that
+    /// share it. Like `triangles()` the edges connected to the bounding vertices are not returned.
+    pub fn edges(&self) -> impl Iterator<Item = (VertexId, VertexId)> + '_ {
+        self.triangles()
+            .flat_map(|(_, t)| {
+                let [a, b, c] = t.vertices;
+                vec![(a, b), (b, c), (c, a)]
+            })
+            .filter(move |&(a, b)| {
+                a < b
+                    || self
+                        .edges
+                        .get(&(b, a))
+                        .is_none_or(|t| self.is_super_triangle(&self.triangles[*t]))
+            })
     }
 
-    fn triangles_boundary<'t>(
+    /// The connected region of triangles that satisfy `in_conflict` starting from the given
+    /// one, which is always part of the region.
+    fn conflict_region(
         &self,
-        triangles: impl IntoIterator<Item = &'t TriangleId>,
-    ) -> impl Iterator<Item = (VertexId, VertexId)> {
-        use std::collections::hash_map::Entry;
+        start: TriangleId,
+        in_conflict: impl Fn(TriangleId) -> bool,
+    ) -> HashSet<TriangleId> {
+        cavity::conflict_region(
+            start,
+            &self.edges,
+            |tid| self.triangles[tid].vertices,
+            in_conflict,
+        )
+    }
+
+    /// The triangles that have the given vertex as one of their vertices in counter-clockwise
+    /// order.
+    fn vertex_star(&self, vid: VertexId) -> Vec<TriangleId> {
+        let vertex = &self.vertices[vid];
+        if vertex.hidden {
+            return vec![];
+        }
+
+        let first = match vertex.triangle {
+            Some(t) => t,
+            None => return vec![],
+        };
+        debug_assert!(self.triangles[first].vertices.contains(&vid));
+
+        let mut star = vec![first];
+        loop {
+            let [_, _, c] = self.triangle_vertices_from(*star.last().unwrap(), vid);
+
+            match self.edges.get(&(vid, c)) {
+                Some(&next) if next != first => star.push(next),
+                _ => break,
+            }
+        }
+
+        star
+    }
+
+    /// The vertices of the triangle rotated so that the given vertex is the first one.
+    fn triangle_vertices_from(&self, tid: TriangleId, vid: VertexId) -> [VertexId; 3] {
+        let [a, b, c] = self.triangles[tid].vertices;
+
+        if a == vid {
+            [a, b, c]
+        } else if b == vid {
+            [b, c, a]
+        } else {
+            [c, a, b]
+        }
+    }
+
+    /// Remove all the triangles around the given vertex and triangulate the hole they leave
+    /// behind. The vertex itself is left in the arena.
+    fn remove_vertex_triangles(&mut self, vid: VertexId, star: Vec<TriangleId>) {
+        let mut link = star
+            .iter()
+            .map(|tid| self.triangle_vertices_from(*tid, vid)[1])
+            .collect::<Vec<_>>();
+
+        for tid in star {
+            self.remove_triangle(tid);
+        }
 
         //
-        // the boundary of the roi is the set of the outer edges that are not shared among the
-        // enclosing triangles
+        // The hole is star shaped and therefore it can always be triangulated by clipping its
+        // ears. The resulting triangulation is not necessarily Delaunay, but flipping the
+        // illegal edges fixes that.
         //
-        let mut boundary = HashMap::new();
+        let mut edges = Vec::with_capacity(link.len() * 2);
+        while link.len() > 3 {
+            let n = link.len();
 
-        for tri in triangles.into_iter() {
-            let tri = &self.triangles[*tri];
+            let ear = (0..n).find(|&i| {
+                let [va, vb, vc] = [link[(i + n - 1) % n], link[i], link[(i + 1) % n]];
 
-            for v in 0..tri.vertices.len() {
-                let edge = (tri.vertices[v], tri.vertices[(v + 1) % tri.vertices.len()]);
+                let a = self.vertices[va].position;
+                let b = self.vertices[vb].position;
+                let c = self.vertices[vc].position;
 
-                let entry = match boundary.entry(edge) {
-                    // be sure to also check the opposite edge
-                    Entry::Vacant(_) => {
-                        let eo = (edge.1, edge.0);
-                        boundary.entry(eo)
-                    }
-                    o @ Entry::Occupied(_) => o,
+                orient2d(a, b, c) > 0.0
+                    && link.iter().all(|&v| {
+                        let p = self.vertices[v].position;
+
+                        v == va
+                            || v == vb
+                            || v == vc
+                            || orient2d(a, b, p) < 0.0
+                            || orient2d(b, c, p) < 0.0
+                            || orient2d(c, a, p) < 0.0
+                    })
+            });
+            let i = ear.expect("star shaped polygons always have an ear");
+
+            let [va, vb, vc] = [link[(i + n - 1) % n], link[i], link[(i + 1) % n]];
+            self.insert_triangle(va, vb, vc);
+            edges.extend_from_slice(&[(va, vb), (vb, vc), (vc, va)]);
+
+            link.remove(i);
+        }
+
+        self.insert_triangle(link[0], link[1], link[2]);
+        edges.extend_from_slice(&[(link[0], link[1]), (link[1], link[2]), (link[2], link[0])]);
+
+        self.legalize_edges(edges);
+    }
+
+    /// Check that the internal invariants of the mesh hold and return the first violation, if
+    /// any. See `validate` for the full list of violations.
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        match self.validate().violations.into_iter().next() {
+            None => Ok(()),
+            Some(v) => Err(v),
+        }
+    }
+
+    /// Check the mesh for all the broken invariants: triangles that aren't counter-clockwise,
+    /// degenerate or overlapping triangles, edges that aren't Delaunay, dangling or unused
+    /// vertices, spatial index entries that are out of sync with the triangles and hidden
+    /// vertices that are not redundant. The last check only runs when the triangulation is
+    /// otherwise valid, so a mesh that passes is the regular triangulation of its vertices.
+    ///
+    /// Useful for debugging and testing, _not_ cheap.
+    pub fn validate(&self) -> ValidationReport {
+        let mut violations = vec![];
+
+        let mut indexed = HashSet::new();
+        for (tid, bbox) in self.triangles_index.iter() {
+            match self.triangles.get(*tid) {
+                Some(t) if t.bbox == *bbox => {
+                    indexed.insert(*tid);
+                }
+                _ => violations.push(InvariantViolation::StaleIndexEntry(*tid)),
+            }
+        }
+
+        let mut used_vertices = HashSet::new();
+        let mut overlapping_pairs = HashSet::new();
+        let mut edges: HashMap<(VertexId, VertexId), TriangleId> = HashMap::new();
+        for (tid, t) in self.triangles.enumerate() {
+            if !indexed.contains(&tid) {
+                violations.push(InvariantViolation::NotIndexed(tid));
+            }
+
+            for i in 0..3 {
+                let edge = (t.vertices[i], t.vertices[(i + 1) % 3]);
+                if let Some(other) = edges.insert(edge, tid) {
+                    overlapping_pairs.insert((other, tid));
+                }
+            }
+
+            let mut dangling = false;
+            for &vertex in &t.vertices {
+                used_vertices.insert(vertex);
+
+                if self.vertices.get(vertex).is_none() {
+                    dangling = true;
+                    violations.push(InvariantViolation::DanglingVertex {
+                        triangle: tid,
+                        vertex,
+                    });
+                }
+            }
+            if dangling {
+                continue;
+            }
+
+            let [a, b, c] = self.triangle_vertices(tid);
+            let orientation = orient2d(a, b, c);
+            if orientation == 0.0 {
+                violations.push(InvariantViolation::Degenerate(tid));
+                continue;
+            }
+            if orientation < 0.0 {
+                violations.push(InvariantViolation::NotCounterClockwise(tid));
+                continue;
+            }
+
+            let mut bbox = Bbox::new(a);
+            bbox.expand(b);
+            bbox.expand(c);
+            for &other in self.triangles_index.intersecting(bbox) {
+                // report each pair only once
+                if other == tid || overlapping_pairs.contains(&(other, tid)) {
+                    continue;
+                }
+
+                // the other triangle is reported on its own if it's degenerate or clockwise
+                let overlapping = self.checked_triangle_vertices(other).is_some_and(|o| {
+                    orient2d(o[0], o[1], o[2]) > 0.0 && triangles_overlap([a, b, c], o)
+                });
+                if overlapping {
+                    overlapping_pairs.insert((tid, other));
+                }
+            }
+        }
+
+        violations.extend(
+            overlapping_pairs
+                .into_iter()
+                .map(|(a, b)| InvariantViolation::Overlapping(a, b)),
+        );
+
+        for (tid, t) in self.triangles.enumerate() {
+            if t.vertices.iter().any(|v| self.vertices.get(*v).is_none()) {
+                continue;
+            }
+
+            for i in 0..3 {
+                let edge = (t.vertices[i], t.vertices[(i + 1) % 3]);
+
+                let neighbor = match edges.get(&(edge.1, edge.0)) {
+                    Some(n) => &self.triangles[*n],
+                    None => continue,
                 };
 
-                entry.and_modify(|shared| *shared = true).or_insert(false);
+                let opposite = *neighbor
+                    .vertices
+                    .iter()
+                    .find(|v| **v != edge.0 && **v != edge.1)
+                    .unwrap();
+
+                if self.vertices.get(opposite).is_some() && self.in_conflict(tid, opposite) {
+                    violations.push(InvariantViolation::NonDelaunayEdge {
+                        triangle: tid,
+                        edge,
+                        opposite,
+                    });
+                }
             }
         }
 
-        boundary
-            .into_iter()
-            .filter(|(_, shared)| !*shared)
-            .map(|(edge, _)| edge)
+        for (vid, v) in self.vertices.enumerate() {
+            if !v.hidden && !used_vertices.contains(&vid) {
+                violations.push(InvariantViolation::UnusedVertex(vid));
+            }
+        }
+
+        // the hidden vertices can only be checked against a valid triangulation
+        if violations.is_empty() {
+            let mut hidden = self.hidden_vertices.iter().copied().collect::<Vec<_>>();
+            hidden.sort();
+
+            for vid in hidden {
+                let start = self.enclosing_triangle(self.vertices[vid].position);
+                if start.is_some_and(|&tid| self.cavity(vid, tid).is_some()) {
+                    violations.push(InvariantViolation::NotRedundant(vid));
+                }
+            }
+        }
+
+        ValidationReport { violations }
     }
-}
 
-impl Vertex {
-    fn new(position: Vec2) -> Self {
-        Vertex { position }
+    /// Whether the given vertex lies strictly inside the circumcircle of the given triangle or,
+    /// more generally, if the vertex has a negative power distance from the orthocircle of the
+    /// triangle. Unlike `Circle::contains` this is exact.
+    fn in_conflict(&self, tid: TriangleId, vid: VertexId) -> bool {
+        let weighted = |v: VertexId| {
+            let v = &self.vertices[v];
+            (v.position, v.weight)
+        };
+
+        let [a, b, c] = self.triangles[tid].vertices;
+        power_test(weighted(a), weighted(b), weighted(c), weighted(vid)) > 0.0
     }
 
-    pub fn position(&self) -> Vec2 {
-        self.position
+    /// The positions of the vertices of the given triangle or None if the triangle or any of its
+    /// vertices has been removed.
+    fn checked_triangle_vertices(&self, tid: TriangleId) -> Option<[Vec2<T>; 3]> {
+        let t = self.triangles.get(tid)?;
+
+        let mut vs = [Vec2::zero(); 3];
+        for (v, vid) in vs.iter_mut().zip(t.vertices.iter()) {
+            *v = self.vertices.get(*vid)?.position;
+        }
+
+        Some(vs)
     }
-}
 
-impl Triangle {
-    pub fn vertices(&self) -> [VertexId; 3] {
-        self.vertices
+    fn is_super_triangle(&self, t: &Triangle<T>) -> bool {
+        t.vertices
+            .iter()
+            .any(|v| !self.input_bbox.contains(self.vertices[*v].position))
     }
 
-    pub fn circumcircle(&self) -> Circle {
-        self.circumcircle
+    fn insert_triangle(&mut self, va: VertexId, vb: VertexId, vc: VertexId) -> TriangleId {
+        let a = self.vertices[va].position;
+        let b = self.vertices[vb].position;
+        let c = self.vertices[vc].position;
+
+        // keep all the triangles counter-clockwise regardless of the order of the edges
+        let (vb, vc) = if orient2d(a, b, c) < 0.0 {
+            (vc, vb)
+        } else {
+            (vb, vc)
+        };
+
+        let (circumcircle, bbox) = self.triangle_circumcircle([va, vb, vc]);
+        let tri = self.triangles.push(Triangle {
+            vertices: [va, vb, vc],
+            circumcircle,
+            bbox,
+        });
+
+        self.triangles_index.insert(tri, bbox);
+
+        for &edge in &[(va, vb), (vb, vc), (vc, va)] {
+            self.edges.insert(edge, tri);
+        }
+
+        for &v in &[va, vb, vc] {
+            self.vertices[v].triangle = Some(tri);
+        }
+
+        tri
+    }
+
+    fn update_circumcircle(&mut self, tri: TriangleId) {
+        self.triangles_index.remove(&tri, self.triangles[tri].bbox);
+
+        let (circumcircle, bbox) = self.triangle_circumcircle(self.triangles[tri].vertices);
+        self.triangles[tri].circumcircle = circumcircle;
+        self.triangles[tri].bbox = bbox;
+
+        self.triangles_index.insert(tri, bbox);
+    }
+
+    /// The circumcircle (or orthocircle) of the triangle with the given vertices along with the
+    /// bbox used in the spatial index. The index needs to know both the extent of the triangle
+    /// and of the circumcircle because the orthocircle doesn't necessarily contain the triangle.
+    fn triangle_circumcircle(&self, vertices: [VertexId; 3]) -> (Circle<T>, Bbox<T>) {
+        let [a, b, c] = vertices.map(|v| &self.vertices[v]);
+
+        let circumcircle = Circle::orthocircle(
+            (a.position, a.weight),
+            (b.position, b.weight),
+            (c.position, c.weight),
+        );
+
+        let mut bbox = circumcircle.bbox();
+        bbox.expand(a.position);
+        bbox.expand(b.position);
+        bbox.expand(c.position);
+
+        (circumcircle, bbox)
+    }
+
+    fn remove_triangle(&mut self, tri: TriangleId) {
+        self.triangles_index.remove(&tri, self.triangles[tri].bbox);
+
+        let [va, vb, vc] = self.triangles[tri].vertices;
+        for edge in &[(va, vb), (vb, vc), (vc, va)] {
+            if self.edges.get(edge) == Some(&tri) {
+                self.edges.remove(edge);
+            }
+        }
+
+        self.triangles.remove(tri);
+    }
+
+    fn opposite_vertex(&self, tri: TriangleId, (va, vb): (VertexId, VertexId)) -> VertexId {
+        *self.triangles[tri]
+            .vertices
+            .iter()
+            .find(|v| **v != va && **v != vb)
+            .unwrap()
+    }
+}
+
+impl<T: Scalar> DelaunayMesh<T> {
+    /// Write the mesh in the snapshot format, a compact binary format that preserves the ids of
+    /// the vertices and of the triangles across reloads, see `read_snapshot`.
+    ///
+    /// All the numbers are little-endian, indices are `u64`s where `u64::MAX` means none and
+    /// booleans are single bytes. The layout of version 1 is:
+    ///
+    /// - the magic bytes `DMSH` followed by the version as `u32`;
+    /// - the input bbox as min x, min y, max x and max y `f64`s;
+    /// - the vertices arena, that is the number of slots and the index of the first free slot as
+    ///   indices, followed by each slot. A free slot is a `0` byte followed by the index of the
+    ///   next free slot while an occupied slot is a `1` byte followed by the x, y and weight of
+    ///   the vertex as `f64`s, whether it's hidden and the index of one of its triangles;
+    /// - the triangles arena in the same way, each occupied slot stores the indices of the 3
+    ///   vertices of the triangle in counter-clockwise order.
+    ///
+    /// The coordinates and the weights are stored as `f64`s regardless of the scalar type of the
+    /// mesh, therefore snapshots can be read back with either scalar. The circumcircles, the
+    /// spatial index and the adjacency are rebuilt on load.
+    pub fn write_snapshot(&self, mut out: impl Write) -> io::Result<()> {
+        out.write_all(SNAPSHOT_MAGIC)?;
+        out.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+
+        let (min, max) = (self.input_bbox.min(), self.input_bbox.max());
+        for v in &[min.x, min.y, max.x, max.y] {
+            out.write_all(&v.as_f64().to_le_bytes())?;
+        }
+
+        write_snapshot_arena(&mut out, &self.vertices, |out, v| {
+            for c in &[v.position.x, v.position.y, v.weight] {
+                out.write_all(&c.as_f64().to_le_bytes())?;
+            }
+            out.write_all(&[u8::from(v.hidden)])?;
+            write_snapshot_index(out, v.triangle.map(|t| t.index()))
+        })?;
+
+        write_snapshot_arena(&mut out, &self.triangles, |out, t| {
+            for v in &t.vertices {
+                write_snapshot_index(out, Some(v.index()))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Read a mesh written by `write_snapshot`. The ids of the vertices and of the triangles are
+    /// the same as in the original mesh, including the ids that are going to be reused for new
+    /// vertices and triangles.
+    ///
+    /// An error of kind `InvalidData` is returned if the data is not a valid snapshot, if its
+    /// version is not supported or if the triangles are not a valid triangulation of the bbox, so
+    /// that a corrupted or hand edited snapshot can't produce a mesh that panics later on.
+    pub fn read_snapshot(mut input: impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != SNAPSHOT_MAGIC {
+            return Err(invalid_snapshot("not a mesh snapshot"));
+        }
+
+        let version = u32::from_le_bytes(read_snapshot_bytes(&mut input)?);
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid_snapshot(&format!(
+                "unsupported snapshot version {}",
+                version
+            )));
+        }
+
+        let mut input_bbox = Bbox::new(Vec2::new(
+            read_snapshot_scalar(&mut input)?,
+            read_snapshot_scalar(&mut input)?,
+        ));
+        input_bbox.expand(Vec2::new(
+            read_snapshot_scalar(&mut input)?,
+            read_snapshot_scalar(&mut input)?,
+        ));
+
+        let vertices = read_snapshot_arena(&mut input, |input| {
+            let position = Vec2::new(read_snapshot_scalar(input)?, read_snapshot_scalar(input)?);
+            let weight = read_snapshot_scalar(input)?;
+            let [hidden] = read_snapshot_bytes(input)?;
+            let triangle = read_snapshot_index(input)?.map(ArenaId::new);
+
+            Ok(Vertex {
+                position,
+                weight,
+                hidden: hidden != 0,
+                triangle,
+            })
+        })?;
+
+        let triangles = read_snapshot_arena(&mut input, |input| {
+            let mut vs = [ArenaId::new(0); 3];
+            for v in &mut vs {
+                *v = read_snapshot_index(input)?
+                    .map(ArenaId::new)
+                    .ok_or_else(|| invalid_snapshot("triangle with an invalid vertex"))?;
+            }
+            Ok(vs)
+        })?;
+
+        DelaunayMesh::from_parts(input_bbox, vertices, triangles).map_err(invalid_snapshot)
+    }
+
+    /// Build a mesh from its bbox, its vertices and the vertices of its triangles as loaded from
+    /// a snapshot or deserialized, everything else is computed again. Return an error if the
+    /// parts are not consistent with each other or if the mesh doesn't pass `validate`, since
+    /// `insert` would panic or corrupt it further.
+    fn from_parts(
+        input_bbox: Bbox<T>,
+        vertices: Arena<Vertex<T>, VertexTag>,
+        triangles: Arena<[VertexId; 3], TriangleTag>,
+    ) -> Result<Self, &'static str> {
+        // this also rejects NaNs
+        if !input_bbox.contains(input_bbox.min()) || !input_bbox.contains(input_bbox.max()) {
+            return Err("invalid bbox");
+        }
+
+        if triangles
+            .iter()
+            .any(|t| t.iter().any(|v| vertices.get(*v).is_none()))
+        {
+            return Err("triangle with an invalid vertex");
+        }
+
+        // the triangles and the index are filled below
+        let mut mesh = DelaunayMesh {
+            triangles: Arena::new(),
+            vertices,
+            triangles_index: Bvh::new(input_bbox),
+            edges: HashMap::new(),
+            hidden_vertices: HashSet::new(),
+            input_bbox,
+        };
+
+        mesh.triangles = triangles.map(|&vertices| {
+            let (circumcircle, bbox) = mesh.triangle_circumcircle(vertices);
+            Triangle {
+                vertices,
+                circumcircle,
+                bbox,
+            }
+        });
+
+        // the triangle of hidden vertices might be stale, so it can refer to any slot, while the
+        // other vertices must belong to their triangle
+        let nslots = mesh.triangles.raw().0.len();
+        for (vid, v) in mesh.vertices.enumerate() {
+            let valid = if v.hidden {
+                v.triangle.is_none_or(|t| t.index() < nslots)
+            } else {
+                v.triangle
+                    .and_then(|t| mesh.triangles.get(t))
+                    .is_some_and(|t| t.vertices.contains(&vid))
+            };
+
+            if !valid {
+                return Err("vertex with an invalid triangle");
+            }
+        }
+
+        mesh.rebuild_index();
+        mesh.hidden_vertices = mesh
+            .vertices
+            .enumerate()
+            .filter(|(_, v)| v.hidden)
+            .map(|(vid, _)| vid)
+            .collect();
+        mesh.check_bounding_vertices()?;
+
+        if !mesh.validate().violations.is_empty() {
+            return Err("the triangles are not a valid triangulation");
+        }
+
+        Ok(mesh)
+    }
+
+    // check that the first four vertices are the bounding vertices created by `new`, that they
+    // surround the bbox and that the boundary of the mesh is the quadrilateral they form. Together
+    // with `validate` this guarantees that the triangles cover the bbox and that `insert` always
+    // finds the triangle enclosing the new point.
+    fn check_bounding_vertices(&self) -> Result<(), &'static str> {
+        let (min, max) = (self.input_bbox.min(), self.input_bbox.max());
+        let bounding = [0, 1, 2, 3].map(ArenaId::new);
+
+        for (i, &vid) in bounding.iter().enumerate() {
+            let outside = self.vertices.get(vid).is_some_and(|v| {
+                let p = v.position;
+                let x = if i % 2 == 0 { p.x < min.x } else { p.x > max.x };
+                let y = if i < 2 { p.y < min.y } else { p.y > max.y };
+                x && y && !v.hidden
+            });
+            if !outside {
+                return Err("invalid bounding vertices");
+            }
+        }
+
+        for (vid, v) in self.vertices.enumerate() {
+            let inside = bounding.contains(&vid) || self.input_bbox.contains(v.position);
+            if !inside || !v.weight.is_finite() {
+                return Err("vertex outside of the bbox");
+            }
+        }
+
+        let [tl, tr, bl, br] = bounding;
+        let mut boundary = self
+            .edges
+            .keys()
+            .filter(|(v0, v1)| !self.edges.contains_key(&(*v1, *v0)))
+            .copied()
+            .collect::<Vec<_>>();
+        boundary.sort();
+
+        let mut expected = vec![(tl, tr), (tr, br), (br, bl), (bl, tl)];
+        expected.sort();
+
+        if boundary != expected {
+            return Err("the triangles don't cover the bbox");
+        }
+
+        Ok(())
+    }
+
+    // rebuild the spatial index and the edges from the triangles.
+    fn rebuild_index(&mut self) {
+        let mut bbox = self.input_bbox;
+        bbox.enlarge(T::from_f64(BOUNDING_PADDING));
+
+        self.triangles_index = Bvh::new(bbox);
+        self.edges.clear();
+        for (tid, t) in self.triangles.enumerate() {
+            self.triangles_index.insert(tid, t.bbox);
+
+            let [va, vb, vc] = t.vertices;
+            for &edge in &[(va, vb), (vb, vc), (vc, va)] {
+                self.edges.insert(edge, tid);
+            }
+        }
+    }
+}
+
+fn write_snapshot_arena<T, Tag, W: Write>(
+    out: &mut W,
+    arena: &Arena<T, Tag>,
+    write: impl Fn(&mut W, &T) -> io::Result<()>,
+) -> io::Result<()> {
+    let (slots, first_free) = arena.raw();
+    write_snapshot_index(out, Some(slots.len()))?;
+    write_snapshot_index(out, first_free)?;
+
+    for slot in slots {
+        match slot {
+            Node::Free { next_free } => {
+                out.write_all(&[0])?;
+                write_snapshot_index(out, *next_free)?;
+            }
+            Node::Occupied(v) => {
+                out.write_all(&[1])?;
+                write(out, v)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_snapshot_arena<T, Tag, R: Read>(
+    input: &mut R,
+    read: impl Fn(&mut R) -> io::Result<T>,
+) -> io::Result<Arena<T, Tag>> {
+    let len =
+        read_snapshot_index(input)?.ok_or_else(|| invalid_snapshot("missing arena length"))?;
+    let first_free = read_snapshot_index(input)?;
+
+    // don't trust the length for the allocation, the data might be truncated
+    let mut slots = Vec::with_capacity(len.min(1 << 16));
+    for _ in 0..len {
+        let [tag] = read_snapshot_bytes(input)?;
+        let slot = match tag {
+            0 => Node::Free {
+                next_free: read_snapshot_index(input)?,
+            },
+            1 => Node::Occupied(read(input)?),
+            _ => return Err(invalid_snapshot("invalid arena slot")),
+        };
+        slots.push(slot);
+    }
+
+    Arena::from_raw(slots, first_free).ok_or_else(|| invalid_snapshot("invalid arena free list"))
+}
+
+fn write_snapshot_index(out: &mut impl Write, index: Option<usize>) -> io::Result<()> {
+    let index = index.map_or(SNAPSHOT_NONE, |i| i as u64);
+    out.write_all(&index.to_le_bytes())
+}
+
+fn read_snapshot_index(input: &mut impl Read) -> io::Result<Option<usize>> {
+    match u64::from_le_bytes(read_snapshot_bytes(input)?) {
+        SNAPSHOT_NONE => Ok(None),
+        i => usize::try_from(i)
+            .map(Some)
+            .map_err(|_| invalid_snapshot("index out of range")),
+    }
+}
+
+fn read_snapshot_scalar<T: Scalar>(input: &mut impl Read) -> io::Result<T> {
+    Ok(T::from_f64(f64::from_le_bytes(read_snapshot_bytes(input)?)))
+}
+
+fn read_snapshot_bytes<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    input.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn invalid_snapshot(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(feature = "serde")]
+impl<T: Scalar> TryFrom<MeshData<T>> for DelaunayMesh<T> {
+    type Error = &'static str;
+
+    // the circumcircles and the bboxes of the triangles are computed again rather than trusted
+    fn try_from(data: MeshData<T>) -> Result<Self, Self::Error> {
+        let triangles = data.triangles.map(|t| t.vertices);
+        DelaunayMesh::from_parts(data.input_bbox, data.vertices, triangles)
+    }
+}
+
+impl<T: Scalar> Vertex<T> {
+    fn new(position: Vec2<T>) -> Self {
+        Vertex {
+            position,
+            weight: T::zero(),
+            hidden: false,
+            triangle: None,
+        }
+    }
+
+    pub fn position(&self) -> Vec2<T> {
+        self.position
+    }
+
+    pub fn weight(&self) -> T {
+        self.weight
+    }
+
+    /// Whether the vertex is redundant because of the weights of the other vertices and
+    /// therefore it's not part of the triangulation.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+}
+
+impl<T: Scalar> Triangle<T> {
+    pub fn vertices(&self) -> [VertexId; 3] {
+        self.vertices
+    }
+
+    pub fn circumcircle(&self) -> Circle<T> {
+        self.circumcircle
+    }
+}
+
+/// The squared distance between p and the segment ab.
+fn segment_dist2<T: Scalar>(a: Vec2<T>, b: Vec2<T>, p: Vec2<T>) -> T {
+    let ab = b - a;
+    let ap = p - a;
+
+    let t = (ap.x * ab.x + ap.y * ab.y) / ab.norm2();
+    (a + ab * t.max(T::zero()).min(T::one())).dist2(p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::geo::polygon_area;
+
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_new_is_counter_clockwise() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 50.0));
+
+        let mesh = DelaunayMesh::new(bbox);
+        assert_eq!(mesh.triangles.iter().count(), 2);
+        assert_eq!(mesh.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_insert_duplicate_point() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let vid = mesh.insert(Vec2::new(25.0, 50.0));
+        assert_eq!(mesh.vertices[vid].position, Vec2::new(25.0, 50.0));
+        let triangles = mesh.triangles.iter().count();
+
+        assert_eq!(mesh.insert(Vec2::new(25.0, 50.0)), vid);
+        assert_eq!(mesh.vertices.iter().count(), 5);
+        assert_eq!(mesh.triangles.iter().count(), triangles);
+        assert_eq!(mesh.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn test_try_insert_outside_bbox() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        assert_eq!(mesh.try_insert(Vec2::new(110.0, 50.0)), None);
+        assert_eq!(mesh.try_insert(Vec2::new(50.0, -1_000.0)), None);
+        assert_eq!(mesh.try_insert(Vec2::new(f64::NAN, 50.0)), None);
+        assert_eq!(mesh.try_insert_weighted(Vec2::new(-5.0, 50.0), 1.0), None);
+        assert_eq!(mesh.vertices.iter().count(), 4);
+
+        let vid = mesh.try_insert(Vec2::new(100.0, 100.0)).unwrap();
+        assert!(mesh.vertices().any(|(v, _)| v == vid));
+        assert!(mesh.validate().violations.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "bbox")]
+    fn test_insert_outside_bbox_panics() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        DelaunayMesh::new(bbox).insert(Vec2::new(500.0, 50.0));
+    }
+
+    #[test]
+    fn test_validate_reports_violations() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        mesh.insert(Vec2::new(10.0, 10.0));
+        mesh.insert(Vec2::new(90.0, 20.0));
+        mesh.insert(Vec2::new(50.0, 80.0));
+        assert!(mesh.validate().violations.is_empty());
+
+        let (tid, _) = mesh.triangles().next().unwrap();
+        let [va, vb, vc] = mesh.triangles[tid].vertices;
+
+        // clockwise and not indexed
+        let cw = mesh.triangles.push(Triangle {
+            vertices: [va, vc, vb],
+            circumcircle: mesh.triangles[tid].circumcircle,
+            bbox: mesh.triangles[tid].bbox,
+        });
+        let report = mesh.validate();
+        assert!(report
+            .violations
+            .contains(&InvariantViolation::NotCounterClockwise(cw)));
+        assert!(report
+            .violations
+            .contains(&InvariantViolation::NotIndexed(cw)));
+        mesh.triangles.remove(cw);
+
+        // index entry pointing to a freed slot and unused vertex
+        mesh.remove_triangle(tid);
+        mesh.triangles_index
+            .insert(tid, Bbox::new(mesh.vertices[va].position));
+        let unused = mesh.vertices.push(Vertex::new(Vec2::new(30.0, 30.0)));
+        let report = mesh.validate();
+        assert!(report
+            .violations
+            .contains(&InvariantViolation::StaleIndexEntry(tid)));
+        assert!(report
+            .violations
+            .contains(&InvariantViolation::UnusedVertex(unused)));
+    }
+
+    #[test]
+    fn test_validate_reports_partial_overlap() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        mesh.insert(Vec2::new(10.0, 10.0));
+        mesh.insert(Vec2::new(90.0, 20.0));
+        mesh.insert(Vec2::new(50.0, 80.0));
+        let (tid, _) = mesh
+            .triangles()
+            .find(|(tid, _)| {
+                mesh.triangle_vertices(*tid)
+                    .contains(&Vec2::new(50.0, 80.0))
+            })
+            .unwrap();
+
+        // a thin strip crossing the triangle, no vertex nor centroid of either triangle lies
+        // inside the other and they share no edge
+        let strip = [
+            Vec2::new(-15.0, 75.0),
+            Vec2::new(115.0, 75.0),
+            Vec2::new(115.0, 76.0),
+        ]
+        .map(|p| mesh.vertices.push(Vertex::new(p)));
+        let strip = mesh.insert_triangle(strip[0], strip[1], strip[2]);
+
+        let report = mesh.validate();
+        assert!(report.violations.iter().any(|v| {
+            *v == InvariantViolation::Overlapping(tid, strip)
+                || *v == InvariantViolation::Overlapping(strip, tid)
+        }));
+    }
+
+    #[test]
+    fn test_nearest_vertex_behind_bounding_vertex() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+        // the bounding vertex next to the origin is much closer to it than the only vertex
+        let mut mesh = DelaunayMesh::new(bbox);
+        let far = mesh.insert(Vec2::new(1_000.0, 1_000.0));
+        assert_eq!(mesh.nearest_vertex(Vec2::zero()), Some(far));
+
+        // and with a few more vertices on the opposite corner
+        let near = mesh.insert(Vec2::new(900.0, 950.0));
+        mesh.insert(Vec2::new(1_000.0, 900.0));
+        assert_eq!(mesh.nearest_vertex(Vec2::new(0.0, 600.0)), Some(near));
+    }
+
+    #[test]
+    fn test_flip_edge() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        mesh.insert(Vec2::new(10.0, 10.0));
+        mesh.insert(Vec2::new(90.0, 20.0));
+        mesh.insert(Vec2::new(50.0, 80.0));
+        mesh.insert(Vec2::new(50.0, 40.0));
+
+        // there's no edge between a vertex and itself
+        let (v, _) = mesh.vertices().next().unwrap();
+        assert_eq!(mesh.flip_edge(v, v), None);
+
+        let edges = mesh
+            .edges
+            .keys()
+            .cloned()
+            .filter(|&(va, vb)| mesh.vertices().any(|(v, _)| v == va || v == vb))
+            .collect::<Vec<_>>();
+        let (va, vb, t0) = edges
+            .into_iter()
+            .find_map(|(va, vb)| {
+                let [t0, _] = mesh.flip_edge(va, vb)?;
+                Some((va, vb, t0))
+            })
+            .unwrap();
+
+        assert!(!mesh.edges.contains_key(&(va, vb)));
+        assert!(mesh
+            .validate()
+            .violations
+            .iter()
+            .all(|v| matches!(v, InvariantViolation::NonDelaunayEdge { .. })));
+
+        // flipping back restores the original edge
+        let (vc, vd) = {
+            let t = &mesh.triangles[t0];
+            (t.vertices[0], t.vertices[2])
+        };
+        assert!(mesh.flip_edge(vc, vd).is_some());
+        assert!(mesh.edges.contains_key(&(va, vb)));
+        assert_eq!(mesh.check_invariants(), Ok(()));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_legalize_restores_delaunay(
+            pts in prop::collection::vec((0_u32..1_000, 0_u32..1_000), 1..50),
+            flips in prop::collection::vec(any::<prop::sample::Index>(), 1..50),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            for (x, y) in pts {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+
+            for ix in flips {
+                let edges = mesh.edges.keys().cloned().collect::<Vec<_>>();
+                let (va, vb) = edges[ix.index(edges.len())];
+                mesh.flip_edge(va, vb);
+            }
+
+            mesh.legalize();
+            prop_assert_eq!(mesh.validate(), ValidationReport::default());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_move_vertex_preserves_ids_and_invariants(
+            pts in prop::collection::vec((0_u32..1_000, 0_u32..1_000), 1..50),
+            moves in prop::collection::vec(
+                (any::<prop::sample::Index>(), -50_i32..50, -50_i32..50, any::<bool>()),
+                1..30,
+            ),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            let ids = pts
+                .into_iter()
+                .map(|(x, y)| mesh.insert(Vec2::new(x.into(), y.into())))
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+
+            for (ix, dx, dy, far) in moves {
+                let vid = ids[ix.index(ids.len())];
+                let old = mesh.vertex(vid).position();
+
+                let d = Vec2::new(dx.into(), dy.into()) * if far { 10.0 } else { 0.1 };
+                let p = old + d;
+                let taken = mesh.find_vertex(p).is_some_and(|v| v != vid);
+
+                let moved = mesh.move_vertex(vid, p);
+                prop_assert_eq!(moved, bbox.contains(p) && !taken);
+                prop_assert_eq!(mesh.vertex(vid).position(), if moved { p } else { old });
+                prop_assert_eq!(mesh.validate(), ValidationReport::default());
+            }
+
+            prop_assert_eq!(
+                mesh.vertices().map(|(v, _)| v).collect::<HashSet<_>>(),
+                ids.into_iter().collect::<HashSet<_>>()
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_roundtrip() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for i in 0..50 {
+            let (x, y) = ((i * 37 % 101) as f64, (i * 61 % 97) as f64);
+            mesh.insert_weighted(Vec2::new(x, y), f64::from(i % 3));
+        }
+        let moved = mesh.vertices().nth(10).unwrap().0;
+        mesh.move_vertex(moved, Vec2::new(50.5, 50.5));
+
+        let json = serde_json::to_string(&mesh).unwrap();
+        let mut loaded: DelaunayMesh = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.validate(), ValidationReport::default());
+
+        let triangles = |m: &DelaunayMesh| {
+            m.triangles()
+                .map(|(tid, t)| (tid, t.vertices()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(triangles(&loaded), triangles(&mesh));
+
+        // the loaded mesh keeps working, triangle ids depend on the order of the edits though
+        let faces = |m: &DelaunayMesh| {
+            m.triangles()
+                .map(|(_, t)| {
+                    let mut vs = t.vertices();
+                    let min = (0..3).min_by_key(|i| vs[*i]).unwrap();
+                    vs.rotate_left(min);
+                    vs
+                })
+                .collect::<HashSet<_>>()
+        };
+        for &(x, y) in &[(3.5, 7.25), (99.0, 1.0), (42.0, 42.0)] {
+            let p = Vec2::new(x, y);
+            assert_eq!(loaded.insert(p), mesh.insert(p));
+        }
+        assert_eq!(faces(&loaded), faces(&mesh));
+        assert_eq!(loaded.validate(), ValidationReport::default());
+    }
+
+    #[test]
+    fn test_snapshot_errors() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        mesh.insert(Vec2::new(3.0, 4.0));
+
+        let mut snapshot = vec![];
+        mesh.write_snapshot(&mut snapshot).unwrap();
+        assert!(DelaunayMesh::<f64>::read_snapshot(&snapshot[..]).is_ok());
+
+        let err = DelaunayMesh::<f64>::read_snapshot(&snapshot[..snapshot.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut wrong_version = snapshot.clone();
+        wrong_version[4] = 2;
+        let err = DelaunayMesh::<f64>::read_snapshot(&wrong_version[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "unsupported snapshot version 2");
+
+        // the last index is the third vertex of the last triangle
+        let mut dangling = snapshot.clone();
+        let n = dangling.len();
+        dangling[n - 8..].copy_from_slice(&1000_u64.to_le_bytes());
+        let err = DelaunayMesh::<f64>::read_snapshot(&dangling[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = DelaunayMesh::<f64>::read_snapshot(&b"PNG\0"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_rejects_inconsistent_data() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        mesh.insert(Vec2::new(3.0, 4.0));
+
+        let json = serde_json::to_value(&mesh).unwrap();
+        let load = |json: &serde_json::Value| serde_json::from_value::<DelaunayMesh>(json.clone());
+
+        let (v0, _) = mesh.vertices.enumerate().next().unwrap();
+        let (t0, _) = mesh
+            .triangles
+            .enumerate()
+            .find(|(_, t)| t.vertices.contains(&v0))
+            .unwrap();
+        let (other, _) = mesh
+            .triangles
+            .enumerate()
+            .find(|(_, t)| !t.vertices.contains(&v0))
+            .unwrap();
+
+        // the circumcircles are computed again rather than trusted
+        let mut wrong_circle = json.clone();
+        wrong_circle["triangles"]["data"][t0.index()]["Occupied"]["circumcircle"]["radius"] =
+            0.0.into();
+        let loaded = load(&wrong_circle).unwrap();
+        assert_eq!(
+            loaded.triangle(t0).circumcircle(),
+            mesh.triangle(t0).circumcircle()
+        );
+        assert_eq!(loaded.validate(), ValidationReport::default());
+
+        let mut dangling = json.clone();
+        dangling["triangles"]["data"][t0.index()]["Occupied"]["vertices"][0] = 1000.into();
+        let err = load(&dangling).unwrap_err();
+        assert!(err.to_string().contains("triangle with an invalid vertex"));
+
+        let mut wrong_triangle = json.clone();
+        wrong_triangle["vertices"]["data"][v0.index()]["Occupied"]["triangle"] =
+            other.index().into();
+        let err = load(&wrong_triangle).unwrap_err();
+        assert!(err.to_string().contains("vertex with an invalid triangle"));
+
+        let mut free_list = json.clone();
+        free_list["vertices"]["first_free"] = v0.index().into();
+        let err = load(&free_list).unwrap_err();
+        assert!(err.to_string().contains("invalid arena free list"));
+
+        let mut bbox = json.clone();
+        bbox["input_bbox"]["min"]["x"] = 20.0.into();
+        let err = load(&bbox).unwrap_err();
+        assert!(err.to_string().contains("invalid bbox"));
+
+        // a bounding vertex moved inside the bbox
+        let mut bounding = json.clone();
+        bounding["vertices"]["data"][v0.index()]["Occupied"]["position"]["x"] = 5.0.into();
+        let err = load(&bounding).unwrap_err();
+        assert!(err.to_string().contains("invalid bounding vertices"));
+
+        // clockwise triangles used to load fine and then panic on the next insert
+        let mut clockwise = json;
+        for slot in clockwise["triangles"]["data"].as_array_mut().unwrap() {
+            if let Some(vertices) = slot["Occupied"]["vertices"].as_array_mut() {
+                vertices.swap(1, 2);
+            }
+        }
+        let err = load(&clockwise).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("the triangles don't cover the bbox"));
+    }
+
+    #[test]
+    fn test_snapshot_vertex_with_wrong_triangle() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        mesh.insert(Vec2::new(3.0, 4.0));
+
+        let mut snapshot = vec![];
+        mesh.write_snapshot(&mut snapshot).unwrap();
+
+        // the triangle of the first vertex comes after the header, the bbox, the length and
+        // the free list of the arena, the slot tag, the position, the weight and the hidden flag
+        let offset = 8 + 32 + 16 + 1 + 16 + 8 + 1;
+        let (v0, _) = mesh.vertices.enumerate().next().unwrap();
+        let (other, _) = mesh
+            .triangles
+            .enumerate()
+            .find(|(_, t)| !t.vertices.contains(&v0))
+            .unwrap();
+
+        for &triangle in &[other.index() as u64, 1000, u64::MAX] {
+            let mut corrupted = snapshot.clone();
+            corrupted[offset..offset + 8].copy_from_slice(&triangle.to_le_bytes());
+
+            let err = DelaunayMesh::<f64>::read_snapshot(&corrupted[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.to_string(), "vertex with an invalid triangle");
+        }
+    }
+
+    #[test]
+    fn test_snapshot_rejects_invalid_triangulation() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[(3.0, 4.0), (7.0, 6.0), (2.0, 8.0)] {
+            mesh.insert(Vec2::new(x, y));
+        }
+
+        let mut snapshot = vec![];
+        mesh.write_snapshot(&mut snapshot).unwrap();
+
+        // the vertex slots are 34 bytes long and they come after the header, the bbox, the
+        // length and the free list of the arena, move the first vertex that isn't a bounding
+        // vertex to the corner of the bbox
+        let offset = 8 + 32 + 16 + 4 * 34 + 1;
+        let mut moved = snapshot.clone();
+        moved[offset..offset + 8].copy_from_slice(&10.0_f64.to_le_bytes());
+        moved[offset + 8..offset + 16].copy_from_slice(&0.0_f64.to_le_bytes());
+
+        let err = DelaunayMesh::<f64>::read_snapshot(&moved[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "the triangles are not a valid triangulation"
+        );
+
+        // swap two vertices of the last triangle to make it clockwise
+        let n = snapshot.len();
+        let mut clockwise = snapshot.clone();
+        clockwise[n - 16..n - 8].copy_from_slice(&snapshot[n - 8..]);
+        clockwise[n - 8..].copy_from_slice(&snapshot[n - 16..n - 8]);
+
+        let err = DelaunayMesh::<f64>::read_snapshot(&clockwise[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // a vertex outside of the bbox
+        let mut outside = snapshot;
+        outside[offset..offset + 8].copy_from_slice(&11.0_f64.to_le_bytes());
+
+        let err = DelaunayMesh::<f64>::read_snapshot(&outside[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "vertex outside of the bbox");
+    }
+
+    #[test]
+    fn test_relax_spreads_points() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let min_dist = |mesh: &DelaunayMesh| {
+            let mut min = f64::INFINITY;
+            for (v0, a) in mesh.vertices() {
+                for (v1, b) in mesh.vertices() {
+                    if v0 != v1 {
+                        min = min.min(a.position().dist(b.position()));
+                    }
+                }
+            }
+            min
+        };
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let mut ids = vec![];
+        for y in 0..5 {
+            for x in 0..5 {
+                ids.push(mesh.insert(Vec2::new(f64::from(40 + x), f64::from(40 + y * 2))));
+            }
+        }
+
+        let before = min_dist(&mesh);
+        mesh.relax(10);
+
+        assert!(min_dist(&mesh) > before * 5.0);
+        assert_eq!(mesh.validate(), ValidationReport::default());
+        assert_eq!(mesh.vertices().map(|(v, _)| v).collect::<Vec<_>>(), ids);
+    }
+
+    #[test]
+    fn test_weighted_hides_redundant_vertices() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let a = mesh.insert(Vec2::new(20.0, 20.0));
+        let b = mesh.insert(Vec2::new(80.0, 20.0));
+        let c = mesh.insert(Vec2::new(50.0, 80.0));
+
+        // light point close to a heavy one
+        let heavy = mesh.insert_weighted(Vec2::new(50.0, 40.0), 900.0);
+        let light = mesh.insert(Vec2::new(52.0, 41.0));
+        assert!(!mesh.vertex(heavy).is_hidden());
+        assert!(mesh.vertex(light).is_hidden());
+
+        // an even heavier point hides the previous one
+        let heavier = mesh.insert_weighted(Vec2::new(50.0, 41.0), 1_600.0);
+        assert!(mesh.vertex(heavy).is_hidden());
+
+        assert_eq!(
+            mesh.vertices().map(|(v, _)| v).collect::<HashSet<_>>(),
+            [a, b, c, heavier].iter().cloned().collect::<HashSet<_>>()
+        );
+        assert_eq!(mesh.validate(), ValidationReport::default());
+
+        // power cells are convex polygons around each vertex
+        for (vid, cell) in mesh.power_diagram() {
+            assert!(cell.len() >= 3);
+            assert!(polygon_area(&cell) > 0.0, "{:?} {:?}", vid, cell);
+        }
+    }
+
+    #[test]
+    fn test_move_vertex_restores_hidden_vertices() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        mesh.insert(Vec2::new(20.0, 20.0));
+        mesh.insert(Vec2::new(80.0, 20.0));
+        mesh.insert(Vec2::new(50.0, 80.0));
+
+        let heavy = mesh.insert_weighted(Vec2::new(50.0, 40.0), 900.0);
+        let light = mesh.insert(Vec2::new(52.0, 41.0));
+        assert!(mesh.vertex(light).is_hidden());
+
+        assert!(mesh.move_vertex(heavy, Vec2::new(10.0, 90.0)));
+        assert!(!mesh.vertex(heavy).is_hidden());
+        assert!(!mesh.vertex(light).is_hidden());
+        assert_eq!(mesh.validate(), ValidationReport::default());
+
+        // moving it back hides the light vertex again
+        assert!(mesh.move_vertex(heavy, Vec2::new(50.0, 40.0)));
+        assert!(mesh.vertex(light).is_hidden());
+        assert_eq!(mesh.validate(), ValidationReport::default());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_weighted_insert_is_regular(
+            pts in prop::collection::vec((0_u32..1_000, 0_u32..1_000, 0_u32..2_000), 1..100),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            let ids = pts
+                .into_iter()
+                .map(|(x, y, w)| mesh.insert_weighted(Vec2::new(x.into(), y.into()), w.into()))
+                .collect::<Vec<_>>();
+
+            prop_assert_eq!(mesh.validate(), ValidationReport::default());
+
+            for vid in ids {
+                if !mesh.vertex(vid).is_hidden() {
+                    continue;
+                }
+
+                // hidden vertices must not conflict with the triangles around them
+                let tid = *mesh.enclosing_triangle(mesh.vertex(vid).position()).unwrap();
+                prop_assert!(!mesh.in_conflict(tid, vid));
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_insert_preserves_invariants(
+            pts in prop::collection::vec((0_u32..1_000, 0_u32..1_000), 1..100),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            for (x, y) in pts {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+
+            prop_assert_eq!(mesh.validate(), ValidationReport::default());
+
+            for (tid, _) in mesh.triangles() {
+                let [a, b, c] = mesh.triangle_vertices(tid);
+                prop_assert!(orient2d(a, b, c) > 0.0);
+            }
+
+            let mut undirected = HashSet::new();
+            for (_, t) in mesh.triangles() {
+                let [a, b, c] = t.vertices();
+                for &(v0, v1) in &[(a, b), (b, c), (c, a)] {
+                    undirected.insert((v0.min(v1), v0.max(v1)));
+                }
+            }
+            let edges = mesh.edges().map(|(a, b)| (a.min(b), a.max(b))).collect::<Vec<_>>();
+            prop_assert_eq!(edges.len(), undirected.len());
+            prop_assert_eq!(edges.into_iter().collect::<HashSet<_>>(), undirected);
+        }
+
+        #[test]
+        fn prop_f32_mesh_matches_f64(
+            pts in prop::collection::vec((0_u32..1_000_000, 0_u32..1_000_000), 1..100),
+        ) {
+            let mut bbox = Bbox::new(Vec2::<f32>::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh32 = DelaunayMesh::new(bbox);
+            let mut mesh64 = DelaunayMesh::new(bbox.cast::<f64>());
+            for (x, y) in pts {
+                let p = Vec2::new(x as f32 / 1_000.0, y as f32 / 1_000.0);
+                prop_assert_eq!(mesh32.insert(p), mesh64.insert(p.cast()));
+            }
+
+            prop_assert_eq!(mesh32.validate(), ValidationReport::default());
+            prop_assert_eq!(mesh64.validate(), ValidationReport::default());
+            prop_assert_eq!(mesh32.triangles().count(), mesh64.triangles().count());
+
+            // the ids survive the conversion
+            let cast = mesh32.cast::<f64>();
+            prop_assert_eq!(cast.validate(), ValidationReport::default());
+            prop_assert_eq!(cast.triangles().count(), mesh32.triangles().count());
+            for ((t32, a), (t64, b)) in mesh32.triangles().zip(cast.triangles()) {
+                prop_assert_eq!(t32, t64);
+                prop_assert_eq!(a.vertices(), b.vertices());
+            }
+        }
+
+        #[test]
+        fn prop_snapshot_preserves_ids(
+            pts in prop::collection::vec((0_u32..1_000, 0_u32..1_000, 0_u8..4), 1..80),
+            moves in prop::collection::vec((0_usize..80, 0_u32..1_000, 0_u32..1_000), 0..10),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            let mut vids = vec![];
+            for (x, y, w) in pts {
+                vids.push(mesh.insert_weighted(Vec2::new(x.into(), y.into()), f64::from(w) * 100.0));
+            }
+            for (i, x, y) in moves {
+                mesh.move_vertex(vids[i % vids.len()], Vec2::new(x.into(), y.into()));
+            }
+
+            let mut snapshot = vec![];
+            mesh.write_snapshot(&mut snapshot).unwrap();
+            let mut loaded = DelaunayMesh::read_snapshot(&snapshot[..]).unwrap();
+
+            prop_assert_eq!(format!("{:?}", loaded.vertices), format!("{:?}", mesh.vertices));
+            prop_assert_eq!(format!("{:?}", loaded.triangles), format!("{:?}", mesh.triangles));
+            prop_assert_eq!(loaded.validate(), mesh.validate());
+
+            let p = Vec2::new(500.25, 499.5);
+            prop_assert_eq!(loaded.insert(p), mesh.insert(p));
+            prop_assert_eq!(loaded.validate(), mesh.validate());
+        }
+
+        #[test]
+        fn prop_nearest_vertex_is_the_closest(
+            pts in prop::collection::vec((0_u32..1_000, 0_u32..1_000), 1..100),
+            queries in prop::collection::vec((0_u32..1_000, 0_u32..1_000), 1..20),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            for (x, y) in pts {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+
+            for (x, y) in queries {
+                let q = Vec2::new(f64::from(x) + 0.25, f64::from(y) + 0.5);
+                let nearest = mesh.nearest_vertex(q).unwrap();
+
+                let d = mesh.vertex(nearest).position().dist2(q);
+                prop_assert!(mesh.vertices().all(|(_, v)| v.position().dist2(q) >= d));
+            }
+        }
     }
 }
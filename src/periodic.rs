@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use crate::geo::{Bbox, Vec2};
+use crate::mesh::{DelaunayMesh, TriangleId, Vertex, VertexId};
+
+/// A Delaunay triangulation of a flat torus, that is the bbox wraps around so that the triangles
+/// on one side are connected to the triangles on the opposite side. This is useful to generate
+/// seamless tiles.
+///
+/// Internally each point is inserted 9 times in a regular `DelaunayMesh` that covers the 3x3
+/// tiling of the bbox and only the triangles whose lowest vertex lies in the central tile are
+/// kept. This requires the points to be dense enough so that the triangulation of the central
+/// tile is not affected by the boundary of the tiling, that is no circumcircle can be wider than
+/// half the bbox, see `check_density`. A few dozen well spread points are usually enough.
+///
+/// The mesh is usually created with `DelaunayMesh::new_periodic`.
+#[derive(Debug)]
+pub struct PeriodicDelaunayMesh {
+    mesh: DelaunayMesh,
+    domain: Bbox,
+
+    // map from each vertex of the inner mesh to the vertex in the central tile it is a copy of.
+    canonical: HashMap<VertexId, VertexId>,
+}
+
+/// A triangle of a `PeriodicDelaunayMesh` whose circumcircle is too big for the points to
+/// triangulate the torus, as found by `PeriodicDelaunayMesh::check_density`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooSparse {
+    pub triangle: TriangleId,
+    pub radius: f64,
+}
+
+impl DelaunayMesh {
+    /// Create an empty mesh where the given bbox is a flat torus, that is the triangles wrap
+    /// across its opposite sides. See `PeriodicDelaunayMesh`.
+    pub fn new_periodic(bbox: Bbox) -> PeriodicDelaunayMesh {
+        PeriodicDelaunayMesh::new(bbox)
+    }
+}
+
+impl PeriodicDelaunayMesh {
+    pub fn new(domain: Bbox) -> Self {
+        let d = domain.dimensions();
+
+        let mut tiling = Bbox::new(domain.min() - d);
+        tiling.expand(domain.max() + d);
+
+        PeriodicDelaunayMesh {
+            mesh: DelaunayMesh::new(tiling),
+            domain,
+            canonical: HashMap::new(),
+        }
+    }
+
+    pub fn bbox(&self) -> Bbox {
+        self.domain
+    }
+
+    /// Insert the given point wrapping it around the bbox if it lies outside of it and return
+    /// its id. Like `DelaunayMesh::insert` the id of the existing vertex is returned if there's
+    /// already a vertex at the same position.
+    pub fn insert(&mut self, p: Vec2) -> VertexId {
+        let p = self.wrap(p);
+
+        let vid = self.mesh.insert(p);
+        if self.canonical.contains_key(&vid) {
+            return vid;
+        }
+        self.canonical.insert(vid, vid);
+
+        let d = self.domain.dimensions();
+        for &dy in &[-1.0, 0.0, 1.0] {
+            for &dx in &[-1.0, 0.0, 1.0] {
+                if dx == 0.0 && dy == 0.0 {
+                    continue;
+                }
+
+                let copy = self.mesh.insert(p + Vec2::new(dx * d.x, dy * d.y));
+                self.canonical.insert(copy, vid);
+            }
+        }
+
+        vid
+    }
+
+    /// Wrap the given point around the bbox so that it lies inside of it, that is in
+    /// `[min, max)` along both axes since the max side is the same as the min one.
+    pub fn wrap(&self, p: Vec2) -> Vec2 {
+        let (min, max) = (self.domain.min(), self.domain.max());
+        let d = self.domain.dimensions();
+
+        // the remainder of tiny negative values rounds up to the period itself
+        let wrap = |v: f64, min: f64, max: f64, d: f64| {
+            let v = min + (v - min).rem_euclid(d);
+            if v < max {
+                v
+            } else {
+                min
+            }
+        };
+
+        Vec2::new(wrap(p.x, min.x, max.x, d.x), wrap(p.y, min.y, max.y, d.y))
+    }
+
+    /// The vertices of the mesh in insertion order, they always lie inside the bbox.
+    pub fn vertices(&self) -> impl Iterator<Item = (VertexId, &Vertex)> {
+        self.mesh
+            .vertices()
+            .filter(move |(vid, _)| self.canonical.get(vid) == Some(vid))
+    }
+
+    pub fn vertex(&self, vid: VertexId) -> &Vertex {
+        self.mesh.vertex(vid)
+    }
+
+    /// The triangles of the mesh along with the ids of their vertices. Each triangle is returned
+    /// only once, but its vertices might lie outside the bbox when it wraps around.
+    pub fn triangles(&self) -> impl Iterator<Item = (TriangleId, [VertexId; 3])> + '_ {
+        self.mesh.triangles().filter_map(move |(tid, t)| {
+            let copies = t.vertices();
+
+            let mut vertices = [copies[0]; 3];
+            for (v, copy) in vertices.iter_mut().zip(copies.iter()) {
+                *v = *self.canonical.get(copy)?;
+            }
+
+            // keep only the copy of the triangle whose lowest vertex is in the central tile
+            let lowest = (0..3).min_by_key(|i| vertices[*i]).unwrap();
+            if copies[lowest] != vertices[lowest] {
+                return None;
+            }
+
+            Some((tid, vertices))
+        })
+    }
+
+    /// The positions of the vertices of the given triangle, some of them might lie outside the
+    /// bbox if the triangle wraps around.
+    pub fn triangle_vertices(&self, tid: TriangleId) -> [Vec2; 3] {
+        self.mesh.triangle_vertices(tid)
+    }
+
+    /// Check that the points are dense enough for `triangles` to be a triangulation of the
+    /// torus, that is no circumcircle has a radius bigger than a quarter of the bbox along its
+    /// shortest side, and return the first triangle that is too big otherwise. This is the case
+    /// for a mesh with only a handful of points.
+    pub fn check_density(&self) -> Result<(), TooSparse> {
+        let d = self.domain.dimensions();
+        let max_radius = d.x.min(d.y) / 4.0;
+
+        for (tid, _) in self.triangles() {
+            let radius = self.mesh.triangle(tid).circumcircle().radius;
+            if radius > max_radius {
+                return Err(TooSparse {
+                    triangle: tid,
+                    radius,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    use crate::geo::polygon_area;
+
+    #[test]
+    fn test_wrap() {
+        let mut bbox = Bbox::new(Vec2::new(10.0, 20.0));
+        bbox.expand(Vec2::new(20.0, 40.0));
+
+        let mesh = PeriodicDelaunayMesh::new(bbox);
+        assert_eq!(mesh.wrap(Vec2::new(15.0, 25.0)), Vec2::new(15.0, 25.0));
+        assert_eq!(mesh.wrap(Vec2::new(25.0, 45.0)), Vec2::new(15.0, 25.0));
+        assert_eq!(mesh.wrap(Vec2::new(5.0, 0.0)), Vec2::new(15.0, 20.0));
+        assert_eq!(mesh.wrap(Vec2::new(20.0, 40.0)), Vec2::new(10.0, 20.0));
+
+        // just below the min side the remainder rounds to the period
+        let p = mesh.wrap(Vec2::new(10.0 - 1e-15, 20.0 - 1e-15));
+        assert_eq!(p, Vec2::new(10.0, 20.0));
+    }
+
+    #[test]
+    fn test_check_density() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new_periodic(bbox);
+        assert_eq!(mesh.check_density(), Ok(()));
+
+        mesh.insert(Vec2::new(10.0, 10.0));
+        mesh.insert(Vec2::new(60.0, 40.0));
+        let err = mesh.check_density().unwrap_err();
+        assert!(err.radius > 25.0);
+
+        for y in 0..10 {
+            for x in 0..10 {
+                let shift = if y % 2 == 0 { 0.0 } else { 5.0 };
+                mesh.insert(Vec2::new(
+                    f64::from(x) * 10.0 + shift,
+                    f64::from(y) * 10.0 + 3.0,
+                ));
+            }
+        }
+        assert_eq!(mesh.check_density(), Ok(()));
+
+        let order = mesh.vertices().map(|(v, _)| v).collect::<Vec<_>>();
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(order, sorted);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_triangles_tile_the_torus(
+            pts in prop::collection::hash_set((0_u32..1_000, 0_u32..1_000), 30..80),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(1_000.0, 1_000.0));
+
+            let mut mesh = PeriodicDelaunayMesh::new(bbox);
+            for &(x, y) in &pts {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+
+            prop_assert_eq!(mesh.vertices().count(), pts.len());
+
+            // the Euler characteristic of the torus is zero, hence F = 2V
+            prop_assert_eq!(mesh.triangles().count(), 2 * pts.len());
+
+            let area = mesh
+                .triangles()
+                .map(|(tid, _)| polygon_area(&mesh.triangle_vertices(tid)))
+                .sum::<f64>();
+            prop_assert!((area - bbox.area()).abs() < 1e-6 * bbox.area());
+
+            // every edge is shared by exactly two triangles
+            let mut edges = HashMap::new();
+            for (_, [a, b, c]) in mesh.triangles() {
+                for &(v0, v1) in &[(a, b), (b, c), (c, a)] {
+                    *edges.entry((v0.min(v1), v0.max(v1))).or_insert(0) += 1;
+                }
+            }
+            prop_assert!(edges.values().all(|n| *n == 2));
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::geo::{orient2d, BarycentricCoords, Bbox, Vec2};
+use crate::mesh::{DelaunayMesh, TriangleId, VertexId};
+
+/// A grid of `width` x `height` pixels covering the given window of the plane. Pixels are stored
+/// in row-major order and the first row is the one closest to the minimum y of the window, like
+/// the images produced by the examples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub window: Bbox,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, window: Bbox) -> Self {
+        Grid {
+            width,
+            height,
+            window,
+        }
+    }
+
+    /// Size of a single pixel in the plane.
+    pub fn pixel_size(&self) -> Vec2 {
+        let d = self.window.dimensions();
+        Vec2::new(d.x / self.width as f64, d.y / self.height as f64)
+    }
+
+    /// The point of the plane at the center of the given pixel.
+    pub fn pixel_center(&self, x: usize, y: usize) -> Vec2 {
+        let s = self.pixel_size();
+        self.window.min() + Vec2::new((x as f64 + 0.5) * s.x, (y as f64 + 0.5) * s.y)
+    }
+
+    /// Call `f` with the coordinates of each pixel whose center lies inside the given
+    /// counter-clockwise triangle along with its barycentric coordinates.
+    ///
+    /// Centers lying exactly on an edge are assigned to only one of the triangles sharing it,
+    /// therefore rasterizing all the triangles of a mesh visits each covered pixel exactly once.
+    pub fn rasterize_triangle(
+        &self,
+        triangle: [Vec2; 3],
+        mut f: impl FnMut(usize, usize, BarycentricCoords),
+    ) {
+        if self.width == 0
+            || self.height == 0
+            || BarycentricCoords::triangle(triangle, triangle[0]).is_none()
+        {
+            return;
+        }
+
+        let [a, b, c] = triangle;
+        let mut bbox = Bbox::new(a);
+        bbox.expand(b);
+        bbox.expand(c);
+
+        let s = self.pixel_size();
+        let min = self.window.min();
+        let first = |v: f64, min: f64, s: f64| ((v - min) / s - 0.5).ceil().max(0.0) as usize;
+        let last = |v: f64, min: f64, s: f64, n: usize| {
+            let i = ((v - min) / s - 0.5).floor();
+            if i < 0.0 {
+                None
+            } else {
+                Some((i as usize).min(n - 1))
+            }
+        };
+
+        let (x0, y0) = (
+            first(bbox.min().x, min.x, s.x),
+            first(bbox.min().y, min.y, s.y),
+        );
+        let (x1, y1) = match (
+            last(bbox.max().x, min.x, s.x, self.width),
+            last(bbox.max().y, min.y, s.y, self.height),
+        ) {
+            (Some(x1), Some(y1)) => (x1, y1),
+            _ => return,
+        };
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let p = self.pixel_center(x, y);
+
+                if covers(a, b, p) && covers(b, c, p) && covers(c, a, p) {
+                    f(x, y, BarycentricCoords::triangle(triangle, p).unwrap());
+                }
+            }
+        }
+    }
+}
+
+/// Rasterize the scalar field defined at the vertices of the mesh by linearly interpolating it
+/// over each triangle. Pixels not covered by any triangle are set to `background`.
+pub fn rasterize_field(
+    mesh: &DelaunayMesh,
+    grid: &Grid,
+    field: impl Fn(VertexId) -> f64,
+    background: f32,
+) -> Vec<f32> {
+    let mut pixels = vec![background; grid.width * grid.height];
+
+    for (tid, t) in mesh.triangles() {
+        let [a, b, c] = t.vertices();
+        let values = [field(a), field(b), field(c)];
+
+        grid.rasterize_triangle(mesh.triangle_vertices(tid), |x, y, bary| {
+            pixels[y * grid.width + x] = bary.interpolate(values) as f32;
+        });
+    }
+
+    pixels
+}
+
+/// Rasterize the mesh filling each triangle with a flat RGBA color. The result contains 4 bytes
+/// per pixel and pixels not covered by any triangle are set to `background`.
+pub fn rasterize_colors(
+    mesh: &DelaunayMesh,
+    grid: &Grid,
+    color: impl Fn(TriangleId) -> [u8; 4],
+    background: [u8; 4],
+) -> Vec<u8> {
+    let mut pixels = background.repeat(grid.width * grid.height);
+
+    for (tid, _) in mesh.triangles() {
+        let color = color(tid);
+
+        grid.rasterize_triangle(mesh.triangle_vertices(tid), |x, y, _| {
+            let i = (y * grid.width + x) * 4;
+            pixels[i..i + 4].copy_from_slice(&color);
+        });
+    }
+
+    pixels
+}
+
+/// Whether p lies on the left of the directed edge ab. Points exactly on the edge are covered
+/// only if the edge goes down or exactly right, so that exactly one of the two triangles sharing
+/// the edge covers them.
+fn covers(a: Vec2, b: Vec2, p: Vec2) -> bool {
+    let d = b - a;
+    let e = orient2d(a, b, p);
+
+    e > 0.0 || (e == 0.0 && (d.y < 0.0 || (d.y == 0.0 && d.x > 0.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_rasterize_field() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(10.0, 10.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+            (3.0, 6.0),
+        ] {
+            mesh.insert(Vec2::new(x, y));
+        }
+
+        let mut window = Bbox::new(Vec2::new(-2.0, 0.0));
+        window.expand(Vec2::new(12.0, 10.0));
+        let grid = Grid::new(14, 20, window);
+
+        let field = |v| {
+            let p = mesh.vertex(v).position();
+            2.0 * p.x + p.y
+        };
+        let pixels = rasterize_field(&mesh, &grid, field, -1.0);
+        assert_eq!(pixels.len(), 14 * 20);
+
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let p = grid.pixel_center(x, y);
+                let v = pixels[y * grid.width + x];
+
+                if bbox.contains(p) {
+                    assert!((f64::from(v) - (2.0 * p.x + p.y)).abs() < 1e-4);
+                } else {
+                    assert_eq!(v, -1.0);
+                }
+            }
+        }
+
+        let colors = rasterize_colors(&mesh, &grid, |_| [255, 0, 0, 255], [0; 4]);
+        assert_eq!(colors.len(), 14 * 20 * 4);
+        assert_eq!(&colors[0..4], &[0, 0, 0, 0]);
+        assert_eq!(&colors[8..12], &[255, 0, 0, 255]);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_pixels_are_covered_once(
+            pts in prop::collection::vec((0_u16..=40, 0_u16..=40), 3..40),
+            (width, height) in (1_usize..60, 1_usize..60),
+        ) {
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(40.0, 40.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            for (x, y) in pts {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+
+            let grid = Grid::new(width, height, bbox);
+            let mut counts = vec![0; width * height];
+            for (tid, _) in mesh.triangles() {
+                grid.rasterize_triangle(mesh.triangle_vertices(tid), |x, y, _| {
+                    counts[y * width + x] += 1;
+                });
+            }
+
+            for y in 0..height {
+                for x in 0..width {
+                    let p = grid.pixel_center(x, y);
+                    let count = counts[y * width + x];
+
+                    // centers on the boundary of the mesh might not be covered at all
+                    let strictly_inside = mesh.triangles().any(|(tid, _)| {
+                        let [a, b, c] = mesh.triangle_vertices(tid);
+                        orient2d(a, b, p) > 0.0 && orient2d(b, c, p) > 0.0 && orient2d(c, a, p) > 0.0
+                    });
+                    if strictly_inside {
+                        prop_assert_eq!(count, 1);
+                    } else if mesh.triangle_at(p).is_none() {
+                        prop_assert_eq!(count, 0);
+                    } else {
+                        prop_assert!(count <= 1);
+                    }
+                }
+            }
+        }
+    }
+}
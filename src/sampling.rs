@@ -0,0 +1,362 @@
+use std::f64::consts::PI;
+
+use crate::geo::{polygon_contains, Bbox, Vec2};
+use crate::mesh::{DelaunayMesh, VertexId};
+
+// number of candidates around each active point before it's retired, as suggested by Bridson.
+const POISSON_ATTEMPTS: usize = 30;
+
+/// Fill the given bbox with Poisson-disk samples and insert them in the mesh, see
+/// `poisson_disk`.
+pub fn poisson_disk_bbox(
+    mesh: &mut DelaunayMesh,
+    bbox: Bbox,
+    radius: impl Fn(Vec2) -> f64,
+    seed: u64,
+) -> Vec<VertexId> {
+    let (min, max) = (bbox.min(), bbox.max());
+    let polygon = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+
+    poisson_disk(mesh, &polygon, radius, seed)
+}
+
+/// Fill the given polygon with Poisson-disk samples using Bridson's algorithm and insert them in
+/// the mesh, returning the ids of the new vertices.
+///
+/// Each sample is at least `radius(p)` away from all the other vertices of the mesh including the
+/// ones that were already there, which makes it possible to fill a region incrementally or to
+/// use different densities in different regions. The rejection test uses the point location of
+/// the mesh itself so there's no need for a background grid. The radius should vary slowly for
+/// the samples to be well spaced.
+///
+/// Points where the radius is not positive or not finite are never sampled, since nothing would
+/// stop the sampling there. Only the part of the polygon inside the bbox of the mesh is sampled
+/// and the samples are deterministic for a given seed.
+pub fn poisson_disk(
+    mesh: &mut DelaunayMesh,
+    polygon: &[Vec2],
+    radius: impl Fn(Vec2) -> f64,
+    seed: u64,
+) -> Vec<VertexId> {
+    let mut rng = Rng::new(seed);
+    let mut samples = vec![];
+
+    let bbox = match polygon_bbox(polygon).intersection(mesh.bbox()) {
+        Some(bbox) => bbox,
+        None => return samples,
+    };
+
+    // insert the point if it's a valid sample, candidates outside of the mesh are skipped
+    let try_sample = |mesh: &mut DelaunayMesh, p: Vec2| {
+        let r = radius(p);
+
+        let valid = r > 0.0
+            && r.is_finite()
+            && polygon_contains(polygon, p)
+            && mesh
+                .nearest_vertex(p)
+                .is_none_or(|v| mesh.vertex(v).position().dist(p) >= r);
+
+        if valid {
+            mesh.try_insert(p)
+        } else {
+            None
+        }
+    };
+
+    let mut active = vec![];
+    for _ in 0..POISSON_ATTEMPTS {
+        let p = rng.point_in(bbox);
+        if let Some(vid) = try_sample(mesh, p) {
+            samples.push(vid);
+            active.push(p);
+            break;
+        }
+    }
+
+    while !active.is_empty() {
+        let i = rng.below(active.len());
+        let center = active[i];
+        let r = radius(center);
+
+        // candidates are taken in the annulus between r and 2r around the active point
+        let sample = (0..POISSON_ATTEMPTS).find_map(|_| {
+            let angle = rng.next_f64() * 2.0 * PI;
+            let dist = r * (1.0 + rng.next_f64());
+            let p = center + Vec2::new(angle.cos(), angle.sin()) * dist;
+
+            try_sample(mesh, p).map(|vid| (vid, p))
+        });
+
+        match sample {
+            Some((vid, p)) => {
+                samples.push(vid);
+                active.push(p);
+            }
+            None => {
+                active.swap_remove(i);
+            }
+        }
+    }
+
+    samples
+}
+
+/// Insert `n` blue-noise samples inside the given polygon using Mitchell's best-candidate
+/// algorithm and return their ids. Unlike `poisson_disk` the number of samples is fixed and the
+/// spacing adapts to it.
+///
+/// Each sample is the best of `candidates` random points in the polygon, that is the one farthest
+/// from the existing vertices of the mesh relative to the `density` at that point. Higher
+/// densities produce closer samples.
+///
+/// Only the part of the polygon inside the bbox of the mesh is sampled and the samples are
+/// deterministic for a given seed.
+pub fn best_candidate(
+    mesh: &mut DelaunayMesh,
+    polygon: &[Vec2],
+    n: usize,
+    candidates: usize,
+    density: impl Fn(Vec2) -> f64,
+    seed: u64,
+) -> Vec<VertexId> {
+    let mut rng = Rng::new(seed);
+    let candidates = candidates.max(1);
+
+    let mut samples = vec![];
+    let bbox = match polygon_bbox(polygon).intersection(mesh.bbox()) {
+        Some(bbox) => bbox,
+        None => return samples,
+    };
+    while samples.len() < n {
+        let mut best: Option<(Vec2, f64)> = None;
+        let mut found = 0;
+
+        // bound the attempts in case the polygon has almost no area
+        for _ in 0..candidates * 100 {
+            if found == candidates {
+                break;
+            }
+
+            let p = rng.point_in(bbox);
+            if !polygon_contains(polygon, p) || !mesh.bbox().contains(p) {
+                continue;
+            }
+            found += 1;
+
+            let score = match mesh.nearest_vertex(p) {
+                Some(v) => mesh.vertex(v).position().dist(p) * density(p).sqrt(),
+                None => f64::INFINITY,
+            };
+            if best.is_none_or(|(_, s)| score > s) {
+                best = Some((p, score));
+            }
+        }
+
+        match best.filter(|(_, score)| *score > 0.0) {
+            Some((p, _)) => match mesh.try_insert(p) {
+                Some(vid) => samples.push(vid),
+                None => break,
+            },
+            None => break,
+        }
+    }
+
+    samples
+}
+
+fn polygon_bbox(polygon: &[Vec2]) -> Bbox {
+    let mut bbox = Bbox::new(polygon[0]);
+    for p in &polygon[1..] {
+        bbox.expand(*p);
+    }
+    bbox
+}
+
+/// A small xorshift* pseudo random generator, good enough for sampling and it avoids a
+/// dependency on a full-blown random crate.
+#[derive(Debug, Clone)]
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // splitmix64 to spread the bits of the seed and to avoid the all zeros state
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        Rng { state: z | 1 }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform number in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// Uniform number in [0, n).
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+
+    fn point_in(&mut self, bbox: Bbox) -> Vec2 {
+        let d = bbox.dimensions();
+        bbox.min() + Vec2::new(self.next_f64() * d.x, self.next_f64() * d.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn min_dist(mesh: &DelaunayMesh, samples: &[VertexId]) -> f64 {
+        let mut d = f64::INFINITY;
+        for (i, a) in samples.iter().enumerate() {
+            for b in &samples[i + 1..] {
+                d = d.min(mesh.vertex(*a).position().dist(mesh.vertex(*b).position()));
+            }
+        }
+        d
+    }
+
+    #[test]
+    fn test_variable_density() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let samples = poisson_disk_bbox(&mut mesh, bbox, |p| 2.0 + p.x / 10.0, 42);
+
+        let left = samples
+            .iter()
+            .filter(|v| mesh.vertex(**v).position().x < 50.0)
+            .count();
+        assert!(left > 2 * (samples.len() - left));
+        assert_eq!(mesh.validate(), Default::default());
+    }
+
+    #[test]
+    fn test_best_candidate() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let triangle = [
+            Vec2::new(10.0, 10.0),
+            Vec2::new(90.0, 10.0),
+            Vec2::new(50.0, 90.0),
+        ];
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let samples = best_candidate(&mut mesh, &triangle, 50, 10, |_| 1.0, 7);
+        assert_eq!(samples.len(), 50);
+        assert!(min_dist(&mesh, &samples) > 2.0);
+        for v in &samples {
+            assert!(polygon_contains(&triangle, mesh.vertex(*v).position()));
+        }
+    }
+
+    #[test]
+    fn test_poisson_disk_invalid_radius() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        assert!(poisson_disk_bbox(&mut mesh, bbox, |_| 0.0, 1).is_empty());
+        assert!(poisson_disk_bbox(&mut mesh, bbox, |_| f64::NAN, 1).is_empty());
+
+        // only the right half has a valid radius
+        let samples =
+            poisson_disk_bbox(&mut mesh, bbox, |p| if p.x < 50.0 { -1.0 } else { 5.0 }, 1);
+        assert!(!samples.is_empty());
+        for v in &samples {
+            assert!(mesh.vertex(*v).position().x >= 50.0);
+        }
+    }
+
+    #[test]
+    fn test_polygon_crossing_the_mesh_bbox() {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let triangle = [
+            Vec2::new(-50.0, 20.0),
+            Vec2::new(150.0, 20.0),
+            Vec2::new(50.0, 180.0),
+        ];
+
+        let mut mesh = DelaunayMesh::new(bbox);
+        let samples = poisson_disk(&mut mesh, &triangle, |_| 5.0, 3);
+        assert!(!samples.is_empty());
+
+        let n = samples.len();
+        let samples = best_candidate(&mut mesh, &triangle, 20, 10, |_| 1.0, 3);
+        assert_eq!(samples.len(), 20);
+        assert_eq!(mesh.vertices().count(), n + 20);
+
+        for (_, v) in mesh.vertices() {
+            assert!(bbox.contains(v.position()));
+            assert!(polygon_contains(&triangle, v.position()));
+        }
+        assert_eq!(mesh.validate(), Default::default());
+
+        let outside = [
+            Vec2::new(200.0, 200.0),
+            Vec2::new(300.0, 200.0),
+            Vec2::new(300.0, 300.0),
+        ];
+        assert!(poisson_disk(&mut mesh, &outside, |_| 5.0, 3).is_empty());
+        assert!(best_candidate(&mut mesh, &outside, 5, 10, |_| 1.0, 3).is_empty());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn prop_poisson_disk_is_well_spaced(
+            r in 3_u8..10,
+            existing in prop::collection::vec((0_u8..=100, 0_u8..=100), 0..10),
+            seed in any::<u64>(),
+        ) {
+            let r = f64::from(r);
+
+            let mut bbox = Bbox::new(Vec2::zero());
+            bbox.expand(Vec2::new(100.0, 100.0));
+
+            let mut mesh = DelaunayMesh::new(bbox);
+            for (x, y) in existing {
+                mesh.insert(Vec2::new(x.into(), y.into()));
+            }
+            let samples = poisson_disk_bbox(&mut mesh, bbox, |_| r, seed);
+
+            let all = mesh.vertices().map(|(v, _)| v).collect::<Vec<_>>();
+            prop_assert!(!samples.is_empty());
+            for s in &samples {
+                let p = mesh.vertex(*s).position();
+                prop_assert!(bbox.contains(p));
+
+                for v in &all {
+                    if v != s {
+                        prop_assert!(mesh.vertex(*v).position().dist(p) >= r);
+                    }
+                }
+            }
+
+            // the disks of radius r/2 don't overlap, while the ones of radius 2r cover most of
+            // the bbox
+            let n = samples.len() as f64;
+            prop_assert!(n * PI * r * r / 4.0 <= (100.0 + r) * (100.0 + r));
+            prop_assert!(n * PI * 4.0 * r * r >= 10_000.0);
+        }
+    }
+}
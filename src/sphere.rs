@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use crate::arena::{Arena, ArenaId};
+use crate::cavity;
+use crate::geo::{orient3d, Vec3};
+
+pub type SphericalTriangleId = ArenaId<SphericalTriangle>;
+pub type SphericalVertexId = ArenaId<SphericalVertex>;
+
+/// A Delaunay triangulation of points on the unit sphere, that is no point lies inside the
+/// circumcircle of any triangle where circles are taken on the sphere itself. Unlike projecting
+/// the points on a plane, there's no boundary and therefore the triangulation wraps around the
+/// poles and the antimeridian.
+///
+/// Like `DelaunayMesh` points are inserted incrementally by removing the triangles whose
+/// circumcircles contain the new point and re-triangulating the boundary of the hole. A point
+/// lies inside the circumcircle of a triangle iff it lies above the plane of the triangle and
+/// therefore the triangulation is also the convex hull of the points.
+///
+/// The triangulation is only built once there are at least four points not lying on the same
+/// circle, until then `triangles()` is empty.
+#[derive(Debug, Default)]
+pub struct SphericalDelaunayMesh {
+    triangles: Arena<SphericalTriangle>,
+    vertices: Arena<SphericalVertex>,
+
+    // map from each directed edge to the triangle it belongs to, see `DelaunayMesh`.
+    edges: HashMap<(SphericalVertexId, SphericalVertexId), SphericalTriangleId>,
+
+    // map from the position of each vertex to its id, see `position_key`.
+    positions: HashMap<[u64; 3], SphericalVertexId>,
+
+    // the vertices that have been inserted before the triangulation was built.
+    pending: Vec<SphericalVertexId>,
+
+    // triangle to start the point location from.
+    last_triangle: Option<SphericalTriangleId>,
+}
+
+/// A triangle of the spherical mesh, its vertices are in counter-clockwise order when seen from
+/// outside the sphere.
+#[derive(Debug)]
+pub struct SphericalTriangle {
+    vertices: [SphericalVertexId; 3],
+    circumcenter: Vec3,
+}
+
+#[derive(Debug)]
+pub struct SphericalVertex {
+    position: Vec3,
+
+    // whether the vertex was inserted before the triangulation was built and it then turned out
+    // to be too close to another vertex to be part of it.
+    hidden: bool,
+}
+
+impl SphericalDelaunayMesh {
+    pub fn new() -> Self {
+        SphericalDelaunayMesh::default()
+    }
+
+    /// The vertices of the mesh, hidden vertices are not included.
+    pub fn vertices(&self) -> impl Iterator<Item = (SphericalVertexId, &SphericalVertex)> {
+        self.vertices.enumerate().filter(|(_, v)| !v.hidden)
+    }
+
+    pub fn vertex(&self, vid: SphericalVertexId) -> &SphericalVertex {
+        &self.vertices[vid]
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = (SphericalTriangleId, &SphericalTriangle)> {
+        self.triangles.enumerate()
+    }
+
+    pub fn triangle_vertices(&self, id: SphericalTriangleId) -> [Vec3; 3] {
+        let vs = self.triangles[id].vertices;
+
+        [
+            self.vertices[vs[0]].position,
+            self.vertices[vs[1]].position,
+            self.vertices[vs[2]].position,
+        ]
+    }
+
+    /// Insert the point at the given latitude and longitude in degrees and return its id. If
+    /// there's already a vertex at the same position then its id is returned and the mesh is left
+    /// untouched.
+    ///
+    /// Return None without adding any vertex if the point is so close to an existing vertex that
+    /// rounding puts it inside the convex hull of the vertices, such a point can't be part of the
+    /// triangulation.
+    ///
+    /// Points inserted before the triangulation is built are only checked once it's built, the
+    /// ones that are too close to another vertex are then hidden, see `SphericalVertex::is_hidden`.
+    pub fn insert(&mut self, lat: f64, lon: f64) -> Option<SphericalVertexId> {
+        let p = Vec3::from_lat_lon(lat, lon);
+
+        if let Some(vid) = self.positions.get(&position_key(p)) {
+            return Some(*vid);
+        }
+
+        if self.last_triangle.is_none() {
+            return self.insert_pending(p);
+        }
+
+        // a point that doesn't lie above any triangle lies on the hull already
+        let start = self.locate(p)?;
+        let vp = self.push_vertex(p);
+        self.insert_vertex(vp, start);
+        Some(vp)
+    }
+
+    fn push_vertex(&mut self, p: Vec3) -> SphericalVertexId {
+        let vid = self.vertices.push(SphericalVertex::new(p));
+        self.positions.insert(position_key(p), vid);
+        vid
+    }
+
+    fn insert_pending(&mut self, p: Vec3) -> Option<SphericalVertexId> {
+        let vp = self.push_vertex(p);
+        self.pending.push(vp);
+
+        if self.pending.len() < 4 {
+            return Some(vp);
+        }
+
+        let pos = |v: SphericalVertexId| self.vertices[v].position;
+        let [a, b, c] = [self.pending[0], self.pending[1], self.pending[2]];
+        let d = match self.pending[3..]
+            .iter()
+            .find(|d| orient3d(pos(a), pos(b), pos(c), pos(**d)) != 0.0)
+        {
+            Some(d) => *d,
+            None => return Some(vp),
+        };
+
+        // build the initial tetrahedron with all the faces pointing outwards, that is the
+        // remaining vertex must lie below each face
+        let (b, c) = if orient3d(pos(a), pos(b), pos(c), pos(d)) > 0.0 {
+            (b, c)
+        } else {
+            (c, b)
+        };
+
+        let t = self.insert_triangle(a, b, c);
+        self.insert_triangle(a, d, b);
+        self.insert_triangle(b, d, c);
+        self.insert_triangle(c, d, a);
+        self.last_triangle = Some(t);
+
+        let pending = std::mem::take(&mut self.pending);
+        for vid in pending {
+            if vid == a || vid == b || vid == c || vid == d {
+                continue;
+            }
+
+            let p = self.vertices[vid].position;
+            match self.locate(p) {
+                Some(start) => self.insert_vertex(vid, start),
+                // the id of the new point has not been handed out yet, but the ones of the
+                // previous points have and they must stay valid
+                None if vid == vp => {
+                    self.positions.remove(&position_key(p));
+                    self.vertices.remove(vid);
+                    return None;
+                }
+                None => self.vertices[vid].hidden = true,
+            }
+        }
+
+        Some(vp)
+    }
+
+    fn insert_vertex(&mut self, vp: SphericalVertexId, start: SphericalTriangleId) {
+        let p = self.vertices[vp].position;
+
+        // see `DelaunayMesh::insert`, the triangles to remove form a connected region that
+        // contains the triangle enclosing the new point
+        let vertices = |tid: SphericalTriangleId| self.triangles[tid].vertices;
+        let region =
+            cavity::conflict_region(start, &self.edges, vertices, |tid| self.in_conflict(tid, p));
+        let boundary = cavity::region_boundary(&region, &self.edges, vertices);
+
+        for tid in region {
+            self.remove_triangle(tid);
+        }
+
+        for (v0, v1) in boundary {
+            let t = self.insert_triangle(v0, v1, vp);
+            self.last_triangle = Some(t);
+        }
+    }
+
+    /// Find a triangle whose circumcircle contains the given point by walking towards it, if any.
+    ///
+    /// The walk starts from the triangle closest to the point among the last inserted one and a
+    /// few triangles spread over the arena, that is the jump-and-walk of Mücke, Saias and Zhu.
+    /// With n triangles sampling about n^1/3 of them makes the walk take about n^1/3 steps
+    /// instead of n^1/2 for points in random order.
+    fn locate(&self, p: Vec3) -> Option<SphericalTriangleId> {
+        let mut tid = self.last_triangle.unwrap();
+
+        let nslots = self.triangles.raw().0.len();
+        let nsamples = (nslots as f64).cbrt() as usize;
+        let mut best = self.triangles[tid].circumcenter.dot(p);
+        for i in 0..nsamples {
+            let sample = ArenaId::new(i * nslots / nsamples);
+            if let Some(t) = self.triangles.get(sample) {
+                let d = t.circumcenter.dot(p);
+                if d > best {
+                    best = d;
+                    tid = sample;
+                }
+            }
+        }
+
+        // the walk can get lost when the points all lie on the same hemisphere because then
+        // there's a triangle covering more than half of the sphere, fallback to a linear search
+        // in that case. Each triangle has three edges, so this bounds the walk by the number of
+        // triangles without counting them.
+        for _ in 0..self.edges.len() / 3 {
+            let [va, vb, vc] = self.triangles[tid].vertices;
+
+            let next = [(va, vb), (vb, vc), (vc, va)].iter().find_map(|&(v0, v1)| {
+                let a = self.vertices[v0].position;
+                let b = self.vertices[v1].position;
+
+                if orient3d(a, b, p, Vec3::zero()) < 0.0 {
+                    Some(self.edges[&(v1, v0)])
+                } else {
+                    None
+                }
+            });
+
+            match next {
+                Some(n) => tid = n,
+                None => break,
+            }
+        }
+
+        if self.in_conflict(tid, p) {
+            return Some(tid);
+        }
+
+        self.triangles
+            .enumerate()
+            .find(|(tid, _)| self.in_conflict(*tid, p))
+            .map(|(tid, _)| tid)
+    }
+
+    /// Whether p lies strictly inside the circumcircle of the triangle.
+    fn in_conflict(&self, tid: SphericalTriangleId, p: Vec3) -> bool {
+        let [a, b, c] = self.triangle_vertices(tid);
+        orient3d(a, b, c, p) < 0.0
+    }
+
+    fn insert_triangle(
+        &mut self,
+        va: SphericalVertexId,
+        vb: SphericalVertexId,
+        vc: SphericalVertexId,
+    ) -> SphericalTriangleId {
+        let a = self.vertices[va].position;
+        let b = self.vertices[vb].position;
+        let c = self.vertices[vc].position;
+
+        let circumcenter = (b - a).cross(c - a).normalized();
+        let tri = self.triangles.push(SphericalTriangle {
+            vertices: [va, vb, vc],
+            circumcenter,
+        });
+
+        for &edge in &[(va, vb), (vb, vc), (vc, va)] {
+            self.edges.insert(edge, tri);
+        }
+
+        tri
+    }
+
+    fn remove_triangle(&mut self, tri: SphericalTriangleId) {
+        let [va, vb, vc] = self.triangles[tri].vertices;
+        for edge in &[(va, vb), (vb, vc), (vc, va)] {
+            if self.edges.get(edge) == Some(&tri) {
+                self.edges.remove(edge);
+            }
+        }
+
+        self.triangles.remove(tri);
+    }
+}
+
+// the key of a position in the map of the positions, zeros are normalized so that the keys are
+// equal iff the positions are.
+fn position_key(p: Vec3) -> [u64; 3] {
+    [p.x, p.y, p.z].map(|c| (c + 0.0).to_bits())
+}
+
+impl SphericalVertex {
+    fn new(position: Vec3) -> Self {
+        SphericalVertex {
+            position,
+            hidden: false,
+        }
+    }
+
+    /// Position of the vertex on the unit sphere.
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    /// Latitude and longitude of the vertex in degrees.
+    pub fn lat_lon(&self) -> (f64, f64) {
+        self.position.lat_lon()
+    }
+
+    /// Whether the vertex is not part of the triangulation because it was inserted before the
+    /// triangulation was built and it turned out to be too close to another vertex.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+}
+
+impl SphericalTriangle {
+    pub fn vertices(&self) -> [SphericalVertexId; 3] {
+        self.vertices
+    }
+
+    /// The center of the circumcircle of the triangle on the unit sphere, that is the vertex of
+    /// the spherical Voronoi diagram dual to this triangle.
+    pub fn circumcenter(&self) -> Vec3 {
+        self.circumcenter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_octahedron() {
+        let mut mesh = SphericalDelaunayMesh::new();
+
+        mesh.insert(90.0, 0.0);
+        mesh.insert(0.0, 0.0);
+        mesh.insert(0.0, 90.0);
+        assert_eq!(mesh.triangles().count(), 0);
+
+        mesh.insert(-90.0, 0.0);
+        mesh.insert(0.0, 180.0);
+        let v = mesh.insert(0.0, -90.0);
+        assert!(v.is_some());
+        assert_eq!(mesh.insert(0.0, -90.0), v);
+
+        assert_eq!(mesh.vertices().count(), 6);
+        assert_eq!(mesh.triangles().count(), 8);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_triangulation_is_delaunay(
+            pts in prop::collection::vec((-90_i32..=90, -180_i32..=180), 4..100),
+        ) {
+            let mut mesh = SphericalDelaunayMesh::new();
+            let ids = pts
+                .into_iter()
+                .filter_map(|(lat, lon)| {
+                    let p = Vec3::from_lat_lon(lat.into(), lon.into());
+                    mesh.insert(lat.into(), lon.into()).map(|vid| (vid, p))
+                })
+                .collect::<Vec<_>>();
+
+            let nvertices = mesh.vertices().count();
+            let ntriangles = mesh.triangles().count();
+            prop_assume!(ntriangles > 0);
+
+            // the ids that have been handed out stay valid and the vertices that are not hidden
+            // are all part of the triangulation
+            for (vid, p) in ids {
+                prop_assert_eq!(mesh.vertices.get(vid).map(|v| v.position()), Some(p));
+            }
+            for (vid, _) in mesh.vertices() {
+                prop_assert!(mesh.triangles().any(|(_, t)| t.vertices().contains(&vid)));
+            }
+
+            // the Euler characteristic of the sphere is two, hence F = 2V - 4
+            prop_assert_eq!(ntriangles, 2 * nvertices - 4);
+
+            for (tid, t) in mesh.triangles() {
+                let [a, b, c] = mesh.triangle_vertices(tid);
+
+                for (vid, v) in mesh.vertices() {
+                    if !t.vertices().contains(&vid) {
+                        prop_assert!(orient3d(a, b, c, v.position()) >= 0.0);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use crate::bvh::Bvh;
+use crate::geo::{
+    clip_convex, clip_halfplane, polygon_area, polygon_centroid, BarycentricCoords, Bbox, Vec2,
+    Vec3,
+};
+use crate::mesh::{DelaunayMesh, TriangleId, VertexId};
+
+/// A triangulated irregular network, that is a `DelaunayMesh` whose vertices also carry an
+/// elevation. The triangulation only depends on the x and y coordinates of the points and the
+/// terrain is the piecewise linear surface interpolating the elevations.
+///
+/// Slopes and aspects are expressed in degrees, the aspect is measured clockwise from the
+/// positive y axis (north) like compass bearings.
+#[derive(Debug)]
+pub struct Terrain {
+    mesh: DelaunayMesh,
+    elevations: HashMap<VertexId, f64>,
+}
+
+impl Terrain {
+    pub fn new(bbox: Bbox) -> Self {
+        Terrain {
+            mesh: DelaunayMesh::new(bbox),
+            elevations: HashMap::new(),
+        }
+    }
+
+    /// The underlying 2D triangulation.
+    pub fn mesh(&self) -> &DelaunayMesh {
+        &self.mesh
+    }
+
+    /// Insert a spot height and return the id of its vertex. If there's already a vertex at the
+    /// same position its elevation is replaced.
+    pub fn insert(&mut self, p: Vec2, z: f64) -> VertexId {
+        let vid = self.mesh.insert(p);
+        self.elevations.insert(vid, z);
+        vid
+    }
+
+    pub fn elevation(&self, vid: VertexId) -> f64 {
+        self.elevations[&vid]
+    }
+
+    /// The point in space of the given vertex.
+    pub fn vertex_point(&self, vid: VertexId) -> Vec3 {
+        let p = self.mesh.vertex(vid).position();
+        Vec3::new(p.x, p.y, self.elevation(vid))
+    }
+
+    /// The points in space of the vertices of the given triangle.
+    pub fn triangle_points(&self, tid: TriangleId) -> [Vec3; 3] {
+        let [a, b, c] = self.mesh.triangle(tid).vertices();
+        [
+            self.vertex_point(a),
+            self.vertex_point(b),
+            self.vertex_point(c),
+        ]
+    }
+
+    /// The elevation of the terrain at the given point, or None if the point is not covered by
+    /// any of the triangles of the mesh.
+    pub fn elevation_at(&self, p: Vec2) -> Option<f64> {
+        let tid = self.mesh.triangle_at(p)?;
+
+        let [a, b, c] = self.mesh.triangle(tid).vertices();
+        let bary = BarycentricCoords::triangle(self.mesh.triangle_vertices(tid), p)?;
+
+        Some(bary.interpolate([self.elevation(a), self.elevation(b), self.elevation(c)]))
+    }
+
+    /// The unit normal of the given triangle, it always points upwards.
+    pub fn normal(&self, tid: TriangleId) -> Vec3 {
+        let [a, b, c] = self.triangle_points(tid);
+
+        // triangles are counter-clockwise in the xy plane and therefore the normal has a
+        // positive z
+        (b - a).cross(c - a).normalized()
+    }
+
+    /// The unit normal at the given vertex, that is the average of the normals of the triangles
+    /// around it weighted by their area.
+    pub fn vertex_normal(&self, vid: VertexId) -> Vec3 {
+        self.mesh
+            .vertex_triangles(vid)
+            .into_iter()
+            .map(|tid| {
+                let [a, b, c] = self.triangle_points(tid);
+                (b - a).cross(c - a)
+            })
+            .fold(Vec3::zero(), |acc, n| acc + n)
+            .normalized()
+    }
+
+    /// The slope of the given triangle, that is the angle between the triangle and the xy plane
+    /// going from 0 for flat triangles to 90 for vertical ones.
+    pub fn slope(&self, tid: TriangleId) -> f64 {
+        let n = self.normal(tid);
+        n.x.hypot(n.y).atan2(n.z).to_degrees()
+    }
+
+    /// The aspect of the given triangle, that is the compass direction its downhill slope faces
+    /// in [0, 360). Flat triangles have no aspect.
+    pub fn aspect(&self, tid: TriangleId) -> Option<f64> {
+        let n = self.normal(tid);
+        if n.x == 0.0 && n.y == 0.0 {
+            return None;
+        }
+
+        // the projection of the normal on the xy plane points downhill
+        Some(n.x.atan2(n.y).to_degrees().rem_euclid(360.0))
+    }
+
+    /// The area in space of the given triangle.
+    pub fn triangle_surface_area(&self, tid: TriangleId) -> f64 {
+        let [a, b, c] = self.triangle_points(tid);
+        (b - a).cross(c - a).norm() / 2.0
+    }
+
+    /// The area of the whole terrain surface, always greater or equal than the planimetric area
+    /// of the triangulated region.
+    pub fn surface_area(&self) -> f64 {
+        self.mesh
+            .triangles()
+            .map(|(tid, _)| self.triangle_surface_area(tid))
+            .sum()
+    }
+}
+
+/// The volumes between two terrains as computed by `Terrain::cut_fill`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CutFill {
+    /// Volume of the material to remove, that is where the first terrain is above the second.
+    pub cut: f64,
+
+    /// Volume of the material to add, that is where the first terrain is below the second.
+    pub fill: f64,
+
+    /// Planimetric area of the region covered by both terrains.
+    pub area: f64,
+
+    /// The cells of the overlay of the two triangulations.
+    pub cells: Vec<CutFillCell>,
+}
+
+/// A cell of the overlay of two terrains, that is the convex polygon where a triangle of the first
+/// terrain overlaps a triangle of the second one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CutFillCell {
+    pub before: TriangleId,
+    pub after: TriangleId,
+    pub polygon: Vec<Vec2>,
+    pub cut: f64,
+    pub fill: f64,
+}
+
+impl Terrain {
+    /// Compute the volumes between this terrain and the `after` one over the region covered by
+    /// both. The volumes are exact for the two piecewise linear surfaces because they're computed
+    /// on the overlay of the two triangulations where the difference of the elevations is linear.
+    pub fn cut_fill(&self, after: &Terrain) -> CutFill {
+        let mut index = Bvh::new(after.mesh.bbox());
+        for (tid, _) in after.mesh.triangles() {
+            index.insert(tid, triangle_bbox(after.mesh.triangle_vertices(tid)));
+        }
+
+        let mut result = CutFill::default();
+        for (before_tid, _) in self.mesh.triangles() {
+            let before_tri = self.mesh.triangle_vertices(before_tid);
+
+            let mut candidates = index
+                .intersecting(triangle_bbox(before_tri))
+                .cloned()
+                .collect::<Vec<_>>();
+            candidates.sort();
+            candidates.dedup();
+
+            for after_tid in candidates {
+                let after_tri = after.mesh.triangle_vertices(after_tid);
+
+                let polygon = clip_convex(&before_tri, &after_tri);
+                let area = polygon_area(&polygon);
+                if area <= 0.0 {
+                    continue;
+                }
+
+                // the difference of the elevations is linear over the cell, hence its integral
+                // is the area times the difference at the centroid
+                let dz = |p: Vec2| self.plane_at(before_tid, p) - after.plane_at(after_tid, p);
+                let volume = |piece: Vec<Vec2>| match polygon_centroid(&piece) {
+                    Some(c) => (polygon_area(&piece) * dz(c)).abs(),
+                    None => 0.0,
+                };
+
+                let cut = volume(clip_halfplane(&polygon, dz));
+                let fill = volume(clip_halfplane(&polygon, |p| -dz(p)));
+
+                result.cut += cut;
+                result.fill += fill;
+                result.area += area;
+                result.cells.push(CutFillCell {
+                    before: before_tid,
+                    after: after_tid,
+                    polygon,
+                    cut,
+                    fill,
+                });
+            }
+        }
+
+        result
+    }
+
+    // the elevation of the plane of the given triangle at the given point, which might lie
+    // outside of the triangle.
+    fn plane_at(&self, tid: TriangleId, p: Vec2) -> f64 {
+        let [a, b, c] = self.mesh.triangle(tid).vertices();
+        let bary = BarycentricCoords::triangle(self.mesh.triangle_vertices(tid), p).unwrap();
+        bary.interpolate([self.elevation(a), self.elevation(b), self.elevation(c)])
+    }
+}
+
+fn triangle_bbox([a, b, c]: [Vec2; 3]) -> Bbox {
+    let mut bbox = Bbox::new(a);
+    bbox.expand(b);
+    bbox.expand(c);
+    bbox
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use proptest::prelude::*;
+
+    fn grid_terrain(step: usize, pts: &[(u16, u16)], elevation: impl Fn(Vec2) -> f64) -> Terrain {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut terrain = Terrain::new(bbox);
+        for y in (0..=100).step_by(step) {
+            for x in (0..=100).step_by(step) {
+                let p = Vec2::new(x.into(), y.into());
+                terrain.insert(p, elevation(p));
+            }
+        }
+        for &(x, y) in pts {
+            let p = Vec2::new(x.into(), y.into());
+            terrain.insert(p, elevation(p));
+        }
+        terrain
+    }
+
+    fn plane_terrain(pts: &[(u16, u16)], elevation: impl Fn(Vec2) -> f64) -> Terrain {
+        let mut bbox = Bbox::new(Vec2::zero());
+        bbox.expand(Vec2::new(100.0, 100.0));
+
+        let mut terrain = Terrain::new(bbox);
+        for p in [(0, 0), (100, 0), (100, 100), (0, 100)].iter().chain(pts) {
+            let p = Vec2::new(p.0.into(), p.1.into());
+            terrain.insert(p, elevation(p));
+        }
+        terrain
+    }
+
+    #[test]
+    fn test_slope_and_aspect() {
+        // rises towards west, hence faces east
+        let terrain = plane_terrain(&[(50, 50)], |p| 100.0 - p.x);
+
+        for (tid, _) in terrain.mesh().triangles() {
+            assert!((terrain.slope(tid) - 45.0).abs() < 1e-9);
+            assert!((terrain.aspect(tid).unwrap() - 90.0).abs() < 1e-9);
+        }
+        assert!((terrain.surface_area() - 10_000.0 * 2.0_f64.sqrt()).abs() < 1e-6);
+
+        let flat = plane_terrain(&[], |_| 3.0);
+        for (tid, _) in flat.mesh().triangles() {
+            assert_eq!(flat.slope(tid), 0.0);
+            assert_eq!(flat.aspect(tid), None);
+            assert_eq!(flat.normal(tid), Vec3::new(0.0, 0.0, 1.0));
+        }
+        assert_eq!(flat.surface_area(), 10_000.0);
+        assert_eq!(flat.elevation_at(Vec2::new(101.0, 50.0)), None);
+    }
+
+    #[test]
+    fn test_cut_fill() {
+        let before = grid_terrain(10, &[], |_| 10.0);
+        let after = grid_terrain(25, &[(33, 47)], |p| p.x);
+
+        let cf = before.cut_fill(&after);
+        assert!((cf.area - 10_000.0).abs() < 1e-6);
+        assert!((cf.cut - 5_000.0).abs() < 1e-6);
+        assert!((cf.fill - 405_000.0).abs() < 1e-6);
+
+        let cells_cut = cf.cells.iter().map(|c| c.cut).sum::<f64>();
+        assert!((cells_cut - cf.cut).abs() < 1e-6);
+
+        let same = before.cut_fill(&before);
+        assert_eq!((same.cut, same.fill), (0.0, 0.0));
+        assert_eq!(same.cells.len(), before.mesh().triangles().count());
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_cut_fill_of_planes(
+            before_pts in prop::collection::vec((5_u16..=95, 5_u16..=95), 0..30),
+            after_pts in prop::collection::vec((5_u16..=95, 5_u16..=95), 0..30),
+            (a, b, c) in (-5_i8..5, -5_i8..5, -100_i8..100),
+        ) {
+            let (a, b, c) = (f64::from(a), f64::from(b), f64::from(c));
+
+            let before = grid_terrain(10, &before_pts, |p| p.x - p.y);
+            let after = grid_terrain(20, &after_pts, |p| a * p.x + b * p.y + c);
+
+            let cf = before.cut_fill(&after);
+            prop_assert!((cf.area - 10_000.0).abs() < 1e-6);
+            prop_assert!(cf.cut >= 0.0 && cf.fill >= 0.0);
+
+            // the net volume is the area times the difference at the center of the square
+            let net = 10_000.0 * (50.0 * a + 50.0 * b + c);
+            prop_assert!((cf.fill - cf.cut - net).abs() < 1e-6 * (cf.cut + cf.fill).max(1.0));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_elevation_at_interpolates_planes(
+            pts in prop::collection::vec((0_u16..=100, 0_u16..=100), 0..50),
+            q in (0_u16..=1000, 0_u16..=1000),
+        ) {
+            let elevation = |p: Vec2| 2.0 * p.x - 0.5 * p.y + 7.0;
+            let terrain = plane_terrain(&pts, elevation);
+
+            let q = Vec2::new(f64::from(q.0) / 10.0, f64::from(q.1) / 10.0);
+            if let Some(z) = terrain.elevation_at(q) {
+                prop_assert!((z - elevation(q)).abs() < 1e-6);
+            }
+
+            for (vid, _) in terrain.mesh().vertices() {
+                let n = terrain.vertex_normal(vid);
+                let expected = Vec3::new(-2.0, 0.5, 1.0).normalized();
+                prop_assert!((n - expected).norm() < 1e-9);
+            }
+        }
+    }
+}